@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use dxf2elmt::{ElementsCategory, Names};
+use simple_xml_builder::XMLElement;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+//QET looks for this file in every folder of a collection to find the category names
+const CATEGORY_FILE: &str = "qet_directory";
+
+//Translations for a category can be put next to the .dxf files in this file, as a table of
+//language code = "name" pairs, for example:
+//    en = "Relays"
+//    fr = "Relais"
+const CATEGORY_NAMES_FILE: &str = "qet_directory.toml";
+
+/// Reads a table of translations from a toml file, where each key is a language code and
+/// each value is the name in that language.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read, isn't valid toml, or contains a name which isn't a string
+pub fn read_names(path: &Path) -> Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)
+        .context(format!("Could not read names file {}", path.display()))?;
+    let table: toml::Table = contents
+        .parse()
+        .context(format!("Could not parse names file {}", path.display()))?;
+
+    table
+        .into_iter()
+        .map(|(lang, value)| match value.as_str() {
+            Some(name) => Ok((lang, name.to_owned())),
+            None => anyhow::bail!("The {lang} name in {} has to be a string", path.display()),
+        })
+        .collect()
+}
+
+/// Turns the output folder into a QET element collection, by writing a `qet_directory` file
+/// into `output_root` and every folder between it and the converted .elmt files.
+///
+/// The category names come from (in order of preference) an existing `qet_directory` file in
+/// the matching input folder, which is copied as is, a `qet_directory.toml` file of translations,
+/// or lastly the folder name itself.
+///
+/// # Errors
+///
+/// Returns an error if a names file can't be read, or a `qet_directory` file can't be written
+pub fn write_categories(
+    out_files: &[PathBuf],
+    output_root: &Path,
+    input_root: &Path,
+) -> Result<()> {
+    let mut folders = BTreeSet::new();
+    for out_file in out_files {
+        let mut folder = out_file.parent();
+        while let Some(fldr) = folder {
+            if !fldr.starts_with(output_root) {
+                break;
+            }
+            folders.insert(fldr.to_path_buf());
+            folder = fldr.parent();
+        }
+    }
+
+    for folder in folders {
+        let rel_dir = folder.strip_prefix(output_root).unwrap_or(Path::new(""));
+        let src_dir = input_root.join(rel_dir);
+        let cat_file = folder.join(CATEGORY_FILE);
+
+        let existing = src_dir.join(CATEGORY_FILE);
+        if existing.is_file() {
+            if existing != cat_file {
+                fs::copy(&existing, &cat_file)
+                    .context(format!("Could not copy {}", existing.display()))?;
+            }
+            continue;
+        }
+
+        let folder_name = fs::canonicalize(&folder)
+            .ok()
+            .and_then(|path| path.file_name().map(|nm| nm.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "Collection".into());
+        let mut names = Names::new("en", folder_name);
+
+        let names_file = src_dir.join(CATEGORY_NAMES_FILE);
+        if names_file.is_file() {
+            for (lang, name) in read_names(&names_file)? {
+                names.set(lang, name);
+            }
+        }
+
+        let out_file =
+            File::create(&cat_file).context(format!("Could not create {}", cat_file.display()))?;
+        XMLElement::from(&ElementsCategory::from(names))
+            .write(out_file)
+            .context(format!("Could not write {}", cat_file.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categories() {
+        let root = std::env::temp_dir().join(format!("dxf2elmt-collection-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (input, output) = (root.join("symbols"), root.join("converted"));
+        for folder in ["relays", "switches"] {
+            fs::create_dir_all(input.join(folder)).unwrap();
+            fs::create_dir_all(output.join(folder)).unwrap();
+        }
+        fs::write(
+            input.join("relays").join(CATEGORY_NAMES_FILE),
+            "fr = \"Relais\"\nde = \"Relais\"",
+        )
+        .unwrap();
+        let existing =
+            "<qet-directory><names><name lang=\"en\">Switches</name></names></qet-directory>";
+        fs::write(input.join("switches").join(CATEGORY_FILE), existing).unwrap();
+
+        let out_files = [
+            output.join("relays").join("K1.elmt"),
+            output.join("switches").join("S1.elmt"),
+        ];
+        write_categories(&out_files, &output, &input).unwrap();
+
+        //the translations go along with the folder name
+        let relays = fs::read_to_string(output.join("relays").join(CATEGORY_FILE)).unwrap();
+        assert!(
+            relays.contains(r#"<name lang="en">relays</name>"#),
+            "{relays}"
+        );
+        assert!(
+            relays.contains(r#"<name lang="fr">Relais</name>"#),
+            "{relays}"
+        );
+        assert!(
+            relays.contains(r#"<name lang="de">Relais</name>"#),
+            "{relays}"
+        );
+
+        //an existing category file is copied over as is
+        let switches = fs::read_to_string(output.join("switches").join(CATEGORY_FILE)).unwrap();
+        assert_eq!(switches, existing);
+
+        //and the output folder itself is a category as well
+        let top = fs::read_to_string(output.join(CATEGORY_FILE)).unwrap();
+        assert!(top.contains(r#"<name lang="en">converted</name>"#), "{top}");
+
+        //nothing gets written into the .dxf folders
+        assert!(!input.join("relays").join(CATEGORY_FILE).exists());
+        assert!(!input.join(CATEGORY_FILE).exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
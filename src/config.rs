@@ -0,0 +1,384 @@
+use crate::collection::read_names;
+use anyhow::{anyhow, Context, Result};
+use dxf2elmt::{ConversionOptions, LinkType};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+//Settings for the .dxf files in a folder can be put next to them in this file. The folder
+//settings can be overridden for a single file in a section named after the file, for example:
+//    link_type = "slave"
+//    kind_informations = { state = "NO" }
+//
+//    [files.K1_coil]
+//    link_type = "master"
+//    kind_informations = { type = "coil" }
+const CONFIG_FILE: &str = "dxf2elmt.toml";
+
+//Translations of the element names can be given for the whole folder in a CSV file, with a
+//row for every .dxf file name (without its extension) and a column for every language:
+//    file,en,fr,de
+//    K1_coil,Coil,Bobine,Spule
+const NAMES_CSV_FILE: &str = "names.csv";
+
+//or for a single file next to it, e.g. K1_coil.names.toml, as language code = "name" pairs
+//like the category names of a collection
+const NAMES_FILE_SUFFIX: &str = ".names.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(flatten)]
+    settings: Settings,
+
+    #[serde(default)]
+    files: BTreeMap<String, Settings>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Settings {
+    link_type: Option<String>,
+
+    #[serde(default)]
+    kind_informations: BTreeMap<String, String>,
+}
+
+impl Settings {
+    //A different link type has different kind informations, so any given
+    //for the link type that's being replaced are dropped
+    fn apply(&self, options: &mut ConversionOptions, path: &Path) -> Result<()> {
+        if let Some(link_type) = &self.link_type {
+            options.link_type = link_type
+                .parse::<LinkType>()
+                .map_err(|e| anyhow!("{e} in {}", path.display()))?;
+            options.kind_infos.clear();
+        }
+        options.kind_infos.extend(
+            self.kind_informations
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        Ok(())
+    }
+}
+
+/// The settings given explicitly on the command line. They win over the files next to the
+/// .dxf, so they're applied after [`options_for`].
+#[derive(Debug, Default)]
+pub struct Overrides<'a> {
+    pub link_type: Option<LinkType>,
+    pub kind_infos: &'a [(String, String)],
+    pub names: &'a [(String, String)],
+}
+
+impl Overrides<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.link_type.is_none() && self.kind_infos.is_empty() && self.names.is_empty()
+    }
+
+    //Same as in the config file, a different link type drops the kind informations given
+    //for the one it replaces
+    pub fn apply(&self, options: &mut ConversionOptions) {
+        if let Some(link_type) = self.link_type {
+            if link_type != options.link_type {
+                options.kind_infos.clear();
+            }
+            options.link_type = link_type;
+        }
+        options.kind_infos.extend_from_slice(self.kind_infos);
+        options.names.extend_from_slice(self.names);
+    }
+}
+
+//The fields of a line of a CSV file. A field can be quoted to have commas in it,
+//with "" for a quote inside of the quoted field
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    fields.push(field);
+
+    fields.iter().map(|field| field.trim().to_owned()).collect()
+}
+
+//the translations in the row of a names CSV file for the .dxf file with this stem
+fn read_names_csv(path: &Path, stem: &str) -> Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)
+        .context(format!("Could not read names file {}", path.display()))?;
+
+    //spreadsheets like to start the file off with a byte order mark
+    let mut lines = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|ln| !ln.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let langs = csv_fields(header);
+
+    let row = lines
+        .map(csv_fields)
+        .find(|row| row.first().is_some_and(|file| file == stem))
+        .unwrap_or_default();
+    Ok(langs
+        .into_iter()
+        .zip(row)
+        .skip(1)
+        .filter(|(lang, name)| !lang.is_empty() && !name.is_empty())
+        .collect())
+}
+
+/// The options for converting a .dxf file, with the settings from the files next to it
+/// applied on top of the defaults given on the command line.
+///
+/// The settings from the `dxf2elmt.toml` file in its folder go first, with the section for
+/// the file itself on top of the ones for the whole folder. Then the element names from the
+/// `names.csv` file in the folder, and lastly the names from the `<stem>.names.toml` file.
+/// The settings that were given explicitly on the command line go on top of all of these,
+/// see [`Overrides`].
+///
+/// # Errors
+///
+/// Returns an error if one of the files can't be read, isn't valid, or has an unknown link type
+pub fn options_for<'a>(
+    file_name: &Path,
+    options: &'a ConversionOptions,
+) -> Result<Cow<'a, ConversionOptions>> {
+    let folder = file_name.parent().unwrap_or(Path::new(""));
+    let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+    let config_file = folder.join(CONFIG_FILE);
+    let names_csv_file = folder.join(NAMES_CSV_FILE);
+    let names_file = folder.join(format!("{stem}{NAMES_FILE_SUFFIX}"));
+    if ![&config_file, &names_csv_file, &names_file]
+        .iter()
+        .any(|path| path.is_file())
+    {
+        return Ok(Cow::Borrowed(options));
+    }
+
+    let mut options = options.clone();
+    if config_file.is_file() {
+        let contents = fs::read_to_string(&config_file).context(format!(
+            "Could not read config file {}",
+            config_file.display()
+        ))?;
+        let config: Config = toml::from_str(&contents).context(format!(
+            "Could not parse config file {}",
+            config_file.display()
+        ))?;
+
+        config.settings.apply(&mut options, &config_file)?;
+        if let Some(settings) = config.files.get(stem.as_ref()) {
+            settings.apply(&mut options, &config_file)?;
+        }
+    }
+
+    if names_csv_file.is_file() {
+        options
+            .names
+            .extend(read_names_csv(&names_csv_file, &stem)?);
+    }
+    if names_file.is_file() {
+        options.names.extend(read_names(&names_file)?);
+    }
+
+    Ok(Cow::Owned(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    //a folder of its own for every test, with the given files in it
+    fn test_folder(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let folder =
+            std::env::temp_dir().join(format!("dxf2elmt-config-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        for (name, contents) in files {
+            fs::write(folder.join(name), contents).unwrap();
+        }
+        folder
+    }
+
+    //the options for a file in the folder, on top of the defaults
+    fn options_in(folder: &Path, file: &str) -> ConversionOptions {
+        options_for(&folder.join(file), &ConversionOptions::default())
+            .unwrap()
+            .into_owned()
+    }
+
+    fn kind_infos(options: &ConversionOptions) -> Vec<(&str, &str)> {
+        options
+            .kind_infos
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    fn names(options: &ConversionOptions) -> Vec<(&str, &str)> {
+        options
+            .names
+            .iter()
+            .map(|(lang, name)| (lang.as_str(), name.as_str()))
+            .collect()
+    }
+
+    const CONFIG: &str = r#"
+        link_type = "slave"
+        kind_informations = { state = "NO" }
+
+        [files.K1_coil]
+        link_type = "master"
+        kind_informations = { type = "coil" }
+
+        [files.K1_contact]
+        kind_informations = { number = "2" }
+    "#;
+
+    #[test]
+    fn no_files_keeps_the_options() {
+        let folder = test_folder("no_files", &[]);
+        let options = ConversionOptions::default();
+        let for_file = options_for(&folder.join("K1.dxf"), &options).unwrap();
+        assert!(matches!(for_file, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn folder_settings() {
+        let folder = test_folder("folder_settings", &[(CONFIG_FILE, CONFIG)]);
+        let options = options_in(&folder, "S1.dxf");
+        assert_eq!(options.link_type, LinkType::Slave);
+        assert_eq!(kind_infos(&options), [("state", "NO")]);
+    }
+
+    #[test]
+    fn file_settings_go_on_top_of_the_folder() {
+        let folder = test_folder("file_settings", &[(CONFIG_FILE, CONFIG)]);
+
+        //a different link type drops the kind informations of the folder
+        let options = options_in(&folder, "K1_coil.dxf");
+        assert_eq!(options.link_type, LinkType::Master);
+        assert_eq!(kind_infos(&options), [("type", "coil")]);
+
+        //the same link type adds to them
+        let options = options_in(&folder, "K1_contact.dxf");
+        assert_eq!(options.link_type, LinkType::Slave);
+        assert_eq!(kind_infos(&options), [("state", "NO"), ("number", "2")]);
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let folder = test_folder("command_line", &[(CONFIG_FILE, CONFIG)]);
+        let cli_kind_infos = [("state".to_string(), "NC".to_string())];
+
+        //the kind informations go on top of the ones from the file
+        let mut options = options_in(&folder, "S1.dxf");
+        Overrides {
+            link_type: None,
+            kind_infos: &cli_kind_infos,
+            ..Default::default()
+        }
+        .apply(&mut options);
+        assert_eq!(options.link_type, LinkType::Slave);
+        assert_eq!(kind_infos(&options).last(), Some(&("state", "NC")));
+
+        //and a different link type replaces the one from the file, along with its kind informations
+        let mut options = options_in(&folder, "K1_coil.dxf");
+        Overrides {
+            link_type: Some(LinkType::TermBlock),
+            ..Default::default()
+        }
+        .apply(&mut options);
+        assert_eq!(options.link_type, LinkType::TermBlock);
+        assert!(options.kind_infos.is_empty());
+    }
+
+    #[test]
+    fn bad_config() {
+        let folder = test_folder("bad_link_type", &[(CONFIG_FILE, "link_type = \"coil\"")]);
+        let err = options_for(&folder.join("K1.dxf"), &ConversionOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("unknown link type"), "{err}");
+
+        let folder = test_folder("unknown_field", &[(CONFIG_FILE, "link = \"master\"")]);
+        let err = options_for(&folder.join("K1.dxf"), &ConversionOptions::default()).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown field"), "{err:#}");
+    }
+
+    #[test]
+    fn csv_fields() {
+        assert_eq!(super::csv_fields("file, en ,fr"), ["file", "en", "fr"]);
+        assert_eq!(
+            super::csv_fields(r#"K1,"Coil, 24V","Bobine ""A1""""#),
+            ["K1", "Coil, 24V", r#"Bobine "A1""#]
+        );
+        assert_eq!(super::csv_fields("K1,,"), ["K1", "", ""]);
+    }
+
+    #[test]
+    fn names_csv() {
+        let csv =
+            "\u{feff}file,en,fr,de\n\nK1_coil,Coil,Bobine,Spule\nK1_no,NO contact,,Schliesser\n";
+        let folder = test_folder("names_csv", &[(NAMES_CSV_FILE, csv)]);
+        let path = folder.join(NAMES_CSV_FILE);
+
+        //the byte order mark isn't part of the first column
+        let row = read_names_csv(&path, "K1_coil").unwrap();
+        assert_eq!(
+            row,
+            [("en", "Coil"), ("fr", "Bobine"), ("de", "Spule")].map(|(l, n)| (l.into(), n.into()))
+        );
+
+        //empty cells are left out
+        let row = read_names_csv(&path, "K1_no").unwrap();
+        assert_eq!(
+            row,
+            [("en", "NO contact"), ("de", "Schliesser")].map(|(l, n)| (l.into(), n.into()))
+        );
+
+        assert!(read_names_csv(&path, "K2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn names_toml_goes_on_top_of_the_csv() {
+        let folder = test_folder(
+            "names_order",
+            &[
+                (NAMES_CSV_FILE, "file,en,fr\nK1,Coil,Bobine\n"),
+                ("K1.names.toml", "fr = \"Bobine A1\""),
+            ],
+        );
+        let options = options_in(&folder, "K1.dxf");
+        //the last name for a language is the one that's used
+        assert_eq!(
+            names(&options),
+            [("en", "Coil"), ("fr", "Bobine"), ("fr", "Bobine A1")]
+        );
+
+        //and the names from the command line go last
+        let cli_names = [("fr".to_string(), "Relais".to_string())];
+        let mut options = options;
+        Overrides {
+            names: &cli_names,
+            ..Default::default()
+        }
+        .apply(&mut options);
+        assert_eq!(names(&options).last(), Some(&("fr", "Relais")));
+    }
+}
@@ -1,26 +1,157 @@
-extern crate tempfile;
-
 use anyhow::Context;
-use std::fs::File;
-use std::path::{Path, PathBuf};
-use tempfile::tempfile;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+/// # Errors
+///
+/// Returns an error if the output file, or any of its parent folders can't be created
+pub fn create_file(out_file_name: &Path) -> Result<File, anyhow::Error> {
+    if let Some(parent) = out_file_name.parent() {
+        fs::create_dir_all(parent).context(format!(
+            "Could not create output folder {}",
+            parent.display()
+        ))?;
+    }
+    File::create(out_file_name).context("Could not create output file")
+}
 
-pub fn create_file(
-    verbose_output: bool,
-    _info: bool,
+/// Works out where the .elmt for `file_name` should be written.
+///
+/// Without an output directory the .elmt goes next to the .dxf. With one, the path of the
+/// .dxf relative to `input_root` is mirrored under the output directory. In both cases the
+/// file name comes from `template`, with `{stem}` replaced by the .dxf file name minus its extension.
+#[must_use]
+pub fn output_file_name(
     file_name: &Path,
-) -> Result<File, anyhow::Error> {
-    let old_file_name = file_name.to_string_lossy();
+    output_dir: Option<&Path>,
+    input_root: &Path,
+    template: &str,
+) -> PathBuf {
+    let stem = file_name
+        .file_stem()
+        .unwrap_or(file_name.as_os_str())
+        .to_string_lossy();
+    let out_name = template.replace("{stem}", &stem);
+
+    let parent = file_name.parent().unwrap_or(Path::new(""));
+    match output_dir {
+        None => parent.join(out_name),
+        Some(output_dir) => {
+            //only keep the normal components, so a weird relative path can never
+            //end up writing outside of the output directory
+            let rel_dir: PathBuf = parent
+                .strip_prefix(input_root)
+                .unwrap_or(Path::new(""))
+                .components()
+                .filter(|comp| matches!(comp, Component::Normal(_)))
+                .collect();
+            output_dir.join(rel_dir).join(out_name)
+        }
+    }
+}
+
+/// Finds the deepest folder that contains all of the input files, this is the folder
+/// the output directory tree gets mirrored from.
+#[must_use]
+pub fn common_root(file_names: &[PathBuf]) -> PathBuf {
+    let mut parents = file_names
+        .iter()
+        .map(|file_name| file_name.parent().unwrap_or(Path::new("")));
+
+    let Some(first) = parents.next() else {
+        return PathBuf::new();
+    };
+
+    parents.fold(first.to_path_buf(), |root, parent| {
+        root.components()
+            .zip(parent.components())
+            .take_while(|(r, p)| r == p)
+            .map(|(r, _)| r)
+            .collect()
+    })
+}
 
-    let mut file_name = PathBuf::from(file_name);
-    file_name.set_extension("elmt");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let friendly_file_name = file_name.to_string_lossy();
-    let mut out_file = tempfile().context("Could not create temporary file");
-    if !verbose_output {
-        out_file = File::create(&file_name).context("Could not create output file");
-        println!("{friendly_file_name} was created... \nNow converting {old_file_name}...",);
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
     }
 
-    out_file.context("Could not return output file")
+    #[test]
+    fn next_to_the_dxf() {
+        let out = output_file_name(
+            Path::new("in/sub/K1.dxf"),
+            None,
+            Path::new("in"),
+            "{stem}.elmt",
+        );
+        assert_eq!(out, Path::new("in/sub/K1.elmt"));
+    }
+
+    #[test]
+    fn mirrored_in_the_output_dir() {
+        let out = output_file_name(
+            Path::new("in/sub/K1.dxf"),
+            Some(Path::new("out")),
+            Path::new("in"),
+            "{stem}.elmt",
+        );
+        assert_eq!(out, Path::new("out/sub/K1.elmt"));
+    }
+
+    #[test]
+    fn name_template() {
+        let out = output_file_name(
+            Path::new("in/K1.dxf"),
+            Some(Path::new("out")),
+            Path::new("in"),
+            "qet_{stem}_v2.elmt",
+        );
+        assert_eq!(out, Path::new("out/qet_K1_v2.elmt"));
+    }
+
+    #[test]
+    fn never_outside_of_the_output_dir() {
+        //not under the input root, so it goes straight into the output directory
+        let out = output_file_name(
+            Path::new("other/K1.dxf"),
+            Some(Path::new("out")),
+            Path::new("in"),
+            "{stem}.elmt",
+        );
+        assert_eq!(out, Path::new("out/K1.elmt"));
+
+        let out = output_file_name(
+            Path::new("in/../../K1.dxf"),
+            Some(Path::new("out")),
+            Path::new("in"),
+            "{stem}.elmt",
+        );
+        assert_eq!(out, Path::new("out/K1.elmt"));
+    }
+
+    #[test]
+    fn common_roots() {
+        assert_eq!(
+            common_root(&paths(&["in/a/K1.dxf", "in/b/K2.dxf"])),
+            Path::new("in")
+        );
+        assert_eq!(
+            common_root(&paths(&["../vendor/a/K1.dxf", "../vendor/a/b/K2.dxf"])),
+            Path::new("../vendor/a")
+        );
+        assert_eq!(
+            common_root(&paths(&["/in/a/K1.dxf", "/in/b/K2.dxf"])),
+            Path::new("/in")
+        );
+        //nothing in common
+        assert_eq!(
+            common_root(&paths(&["a/K1.dxf", "b/K2.dxf"])),
+            Path::new("")
+        );
+        assert_eq!(common_root(&paths(&["in/K1.dxf"])), Path::new("in"));
+        assert_eq!(common_root(&[]), Path::new(""));
+    }
 }
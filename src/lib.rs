@@ -0,0 +1,187 @@
+//! Converts .dxf drawings into [QElectroTech](https://qelectrotech.org/) .elmt elements.
+//!
+//! ```no_run
+//! use dxf2elmt::{convert, ConversionOptions};
+//!
+//! let drawing = dxf::Drawing::load_file("relay.dxf").unwrap();
+//! let element = convert("relay", &drawing, &ConversionOptions::default()).unwrap();
+//! println!("{element}");
+//! ```
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    //clippy::cargo,
+    //rust_2024_compatibility,
+)]
+#![allow(clippy::must_use_candidate, clippy::return_self_not_must_use)]
+
+extern crate dxf;
+extern crate simple_xml_builder;
+extern crate unicode_segmentation;
+
+use dxf::{Drawing, DxfError};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+
+mod qelmt;
+pub use qelmt::{
+    BulgeMode, ConversionError, ConversionOptions, ConversionStats, Definition, ElementsCategory,
+    ErrorKind, Hatches, LinkType, Names, TextMode,
+};
+
+/// Why a drawing couldn't be converted.
+///
+/// Entities that can't be converted don't make the whole conversion fail, they're
+/// left out and listed in [`Definition::stats`], unless strict mode is on.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The .dxf file couldn't be read
+    Load(DxfError),
+
+    /// Strict mode is on, and the `skipped` entities couldn't be converted. The stats
+    /// of the conversion are kept, so the rest of what happened can still be reported
+    Incomplete {
+        skipped: Vec<ConversionError>,
+        stats: Box<ConversionStats>,
+    },
+}
+
+impl Error {
+    /// The stats of the conversion, if the drawing got far enough to be converted.
+    pub fn stats(&self) -> Option<&ConversionStats> {
+        match self {
+            Self::Load(_) => None,
+            Self::Incomplete { stats, .. } => Some(stats),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load(e) => write!(f, "{e}"),
+            Self::Incomplete { skipped, .. } => {
+                write!(f, "{} entities couldn't be converted:", skipped.len())?;
+                for sk in skipped {
+                    write!(f, "\n\t{sk}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Load(e) => Some(e),
+            Self::Incomplete { .. } => None,
+        }
+    }
+}
+
+impl From<DxfError> for Error {
+    fn from(e: DxfError) -> Self {
+        Error::Load(e)
+    }
+}
+
+/// Loads a .dxf drawing, along with the hatches in it.
+///
+/// The dxf crate skips over HATCH entities, so they are read separately from the same
+/// data. Hatches are only read from ascii .dxf files.
+///
+/// # Errors
+///
+/// Returns [`Error::Load`] if the data can't be read or isn't a valid .dxf drawing.
+pub fn load(reader: &mut impl Read) -> Result<(Drawing, Hatches), Error> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).map_err(DxfError::IoError)?;
+    let drawing = Drawing::load(&mut Cursor::new(&data))?;
+    Ok((drawing, Hatches::parse(&data)))
+}
+
+/// Loads a .dxf file, along with the hatches in it. See [`load`].
+///
+/// # Errors
+///
+/// Returns [`Error::Load`] if the file can't be opened or isn't a valid .dxf drawing.
+pub fn load_file(path: impl AsRef<Path>) -> Result<(Drawing, Hatches), Error> {
+    let file = File::open(path).map_err(DxfError::IoError)?;
+    load(&mut BufReader::new(file))
+}
+
+/// Converts a drawing into an element called `name`.
+///
+/// Hatches aren't part of a [`Drawing`], use [`convert_with_hatches`] to include them.
+///
+/// # Errors
+///
+/// In strict mode, returns [`Error::Incomplete`] listing every entity that couldn't be
+/// converted, apart from the entity types allowed by the options.
+pub fn convert(
+    name: impl Into<String>,
+    drw: &Drawing,
+    options: &ConversionOptions,
+) -> Result<Definition, Error> {
+    convert_with_hatches(name, drw, &Hatches::default(), options)
+}
+
+/// Converts a drawing, along with the hatches loaded from the same file by [`load`],
+/// into an element called `name`.
+///
+/// # Errors
+///
+/// The same as [`convert`].
+pub fn convert_with_hatches(
+    name: impl Into<String>,
+    drw: &Drawing,
+    hatches: &Hatches,
+    options: &ConversionOptions,
+) -> Result<Definition, Error> {
+    let definition = Definition::new(name, options, drw, hatches);
+
+    if options.strict {
+        let skipped: Vec<ConversionError> = definition
+            .stats()
+            .skipped()
+            .filter(|sk| {
+                !options
+                    .allowed_entities
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(sk.entity_type))
+            })
+            .cloned()
+            .collect();
+
+        if !skipped.is_empty() {
+            return Err(Error::Incomplete {
+                skipped,
+                stats: Box::new(definition.stats().clone()),
+            });
+        }
+    }
+
+    Ok(definition)
+}
+
+/// Loads a .dxf file and converts it into an element named after the file.
+///
+/// # Errors
+///
+/// Returns [`Error::Load`] if the file can't be loaded, otherwise the same as [`convert`].
+pub fn convert_file(
+    path: impl AsRef<Path>,
+    options: &ConversionOptions,
+) -> Result<Definition, Error> {
+    let path = path.as_ref();
+    let (drawing, hatches) = load_file(path)?;
+    let name = path
+        .file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    convert_with_hatches(name, &drawing, &hatches, options)
+}
@@ -6,32 +6,29 @@
 )]
 //#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 
-extern crate dxf;
-extern crate simple_xml_builder;
-extern crate unicode_segmentation;
-
-use anyhow::{Context, Ok, Result};
+use anyhow::{bail, Context, Ok, Result};
 use clap::Parser;
-use dxf::entities::EntityType;
-use dxf::Drawing;
-use qelmt::Definition;
-//use rayon::prelude::*;
-use simple_xml_builder::XMLElement;
+use dxf2elmt::{BulgeMode, ConversionOptions, ConversionStats, LinkType, TextMode};
+use rayon::prelude::*;
+use report::{BatchReport, ElementReport, FileReport};
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::{io, path::PathBuf};
 use tracing::{span, trace, warn, Level};
 use tracing_subscriber::prelude::*;
+use uuid::Uuid;
 
 #[cfg(feature = "venator")]
 use venator::Venator;
 
-mod qelmt;
-
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug)]
 #[command(name = "dxf2elmt")]
 #[command(author, version, about = "A CLI program to convert .dxf files into .elmt files", long_about = None)]
 struct Args {
-    /// The .dxf file to convert
+    /// The .dxf file to convert, - reads the .dxf from stdin
     //#[clap(short, long, value_parser)]
     file_names: Vec<PathBuf>,
 
@@ -39,6 +36,10 @@ struct Args {
     #[clap(short, long, value_parser, default_value_t = false)]
     verbose: bool,
 
+    /// The .elmt file to write to, - writes it to stdout... only works when converting a single file
+    #[clap(short, long, value_parser, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+
     /// Converts text entities into dynamic text instead of the default text box
     #[clap(short, long, value_parser, default_value_t = false)]
     dtext: bool,
@@ -47,12 +48,145 @@ struct Args {
     #[clap(short, long, value_parser, default_value_t = 20)]
     spline_step: u32,
 
+    /// Converts the arcs in polylines (bulges) into arcs, instead of adding points along them to the polygon
+    #[clap(long, value_parser, default_value_t = false)]
+    bulge_arcs: bool,
+
+    /// Overrides the scale factor that is otherwise picked from the drawing units (2 = 1mm per 2px)
+    #[clap(long, value_parser)]
+    scale: Option<f64>,
+
+    /// Comma separated list of layers to convert... defaults to all layers
+    #[clap(long, value_parser, value_delimiter = ',')]
+    layers: Vec<String>,
+
+    /// Comma separated list of layers to leave out
+    #[clap(long, value_parser, value_delimiter = ',')]
+    exclude_layers: Vec<String>,
+
+    /// Number of decimals written out for coordinates and sizes
+    #[clap(long, value_parser, default_value_t = 2)]
+    precision: u8,
+
+    /// How far off (as a fraction) a closed polyline can be from a circle or rectangle and still be converted into one
+    #[clap(long, value_parser, default_value_t = 0.02)]
+    shape_tolerance: f64,
+
+    /// Draws pattern hatches as lines, instead of filling them with the closest QET hatch filling
+    #[clap(long, value_parser, default_value_t = false)]
+    hatch_patterns: bool,
+
     /// Toggles information output... defaults to off
     #[clap(short, long, value_parser, default_value_t = false)]
     info: bool,
+
+    /// Number of files to convert at the same time... defaults to the number of CPU cores
+    #[clap(short, long, value_parser)]
+    jobs: Option<usize>,
+
+    /// Directory to write the .elmt files to, mirroring the folder structure of the .dxf files... defaults to next to each .dxf file
+    #[clap(long, value_parser)]
+    output_dir: Option<PathBuf>,
+
+    /// Template for the .elmt file names, {stem} is replaced with the .dxf file name without its extension
+    #[clap(long, value_parser, default_value = "{stem}.elmt")]
+    name_template: String,
+
+    /// Writes a QET category file in every output folder, so the output can be imported as a QET element collection... needs --output-dir, so the .dxf folders are left alone
+    #[clap(long, value_parser, default_value_t = false, requires = "output_dir")]
+    collection: bool,
+
+    /// Writes a JSON report of the conversion results for every file to this path
+    #[clap(long, value_parser)]
+    report: Option<PathBuf>,
+
+    /// Fails the conversion of any file with entities that couldn't be converted, instead of leaving them out
+    #[clap(long, value_parser, default_value_t = false)]
+    strict: bool,
+
+    /// Comma separated list of dxf entity types (e.g. POINT,XLINE) that are allowed to be left out in strict mode
+    #[clap(long, value_parser, value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Comma separated list of attribute tags mapped to QET element informations (e.g. MFR=manufacturer), on top of the built in ones like LABEL, DESIGNATION, MANUFACTURER and REF
+    #[clap(long, value_parser = parse_pair, value_delimiter = ',')]
+    info_names: Vec<(String, String)>,
+
+    /// Comma separated list of layers with POINT entities that mark where the terminals go
+    #[clap(long, value_parser, value_delimiter = ',')]
+    terminal_layers: Vec<String>,
+
+    /// Comma separated list of blocks that mark where the terminals go, drawn with the conductor coming in from the top
+    #[clap(long, value_parser, value_delimiter = ',')]
+    terminal_blocks: Vec<String>,
+
+    /// How QET links the element to others, e.g. simple, master, slave or terminal [default: simple]. Wins over the link type in the dxf2elmt.toml file next to the .dxf
+    #[clap(long, value_parser)]
+    link_type: Option<LinkType>,
+
+    /// Comma separated list of kind informations for the link type, replacing the defaults QET uses (e.g. state=NC,number=2). Wins over the ones in the dxf2elmt.toml file next to the .dxf
+    #[clap(long, value_parser = parse_pair, value_delimiter = ',')]
+    kind_infos: Vec<(String, String)>,
+
+    /// Comma separated list of element names in other languages (e.g. fr=Relais,de=Relais), the english name defaults to the .dxf file name. Wins over the names in the names.csv and <stem>.names.toml files next to the .dxf
+    #[clap(long, value_parser = parse_pair, value_delimiter = ',')]
+    names: Vec<(String, String)>,
+
+    /// Works the uuids out from the .dxf file path and contents instead of making up random ones, so converting the same .dxf again gives exactly the same .elmt
+    #[clap(long, value_parser, default_value_t = false)]
+    deterministic: bool,
 }
 
+impl Args {
+    //the settings given on the command line that win over the files next to the .dxf
+    fn overrides(&self) -> config::Overrides<'_> {
+        config::Overrides {
+            link_type: self.link_type,
+            kind_infos: &self.kind_infos,
+            names: &self.names,
+        }
+    }
+
+    //when the xml goes to stdout everything else has to go to stderr, otherwise
+    //the output can't be piped into anything
+    fn to_stdout(&self) -> bool {
+        self.verbose
+            || self.output.as_deref() == Some(Path::new("-"))
+            || (self.output.is_none() && self.file_names.iter().any(|f| is_stdin(f)))
+    }
+}
+
+//a name and the value that goes with it, given as NAME=value
+fn parse_pair(arg: &str) -> Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() && !value.trim().is_empty() => {
+            Ok((name.trim().into(), value.trim().into()))
+        }
+        _ => bail!("expected NAME=value, found \"{arg}\""),
+    }
+}
+
+//The namespace for the uuids of an element, worked out from where the .dxf file is (relative
+//to the folder being converted, so the files can be moved around together) and its contents
+fn uuid_namespace(file_name: &Path, input_root: &Path, data: &[u8]) -> Uuid {
+    let rel_path: Vec<String> = file_name
+        .strip_prefix(input_root)
+        .unwrap_or(file_name)
+        .components()
+        .map(|comp| comp.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let path_namespace = Uuid::new_v5(&Uuid::NAMESPACE_URL, rel_path.join("/").as_bytes());
+    Uuid::new_v5(&path_namespace, data)
+}
+
+fn is_stdin(file_name: &Path) -> bool {
+    file_name == Path::new("-")
+}
+
+mod collection;
+mod config;
 pub mod file_writer;
+mod report;
 
 #[allow(clippy::too_many_lines)]
 fn main() -> Result<()> {
@@ -100,108 +234,274 @@ fn main() -> Result<()> {
     // Collect arguments
     let args: Args = Args::parse_from(wild::args());
 
+    let mut options = ConversionOptions::default();
+    options.text_mode = if args.dtext {
+        TextMode::Dynamic
+    } else {
+        TextMode::Static
+    };
+    options.spline_step = args.spline_step;
+    options.bulge_mode = if args.bulge_arcs {
+        BulgeMode::Arcs
+    } else {
+        BulgeMode::Points
+    };
+    options.scale = args.scale;
+    options.include_layers.clone_from(&args.layers);
+    options.exclude_layers.clone_from(&args.exclude_layers);
+    options.precision = args.precision;
+    options.shape_tolerance = args.shape_tolerance;
+    options.hatch_patterns = args.hatch_patterns;
+    options.strict = args.strict;
+    options.allowed_entities.clone_from(&args.allow);
+
+    //the first match wins, so the tags given on the command line go in front of the built in ones
+    options.info_names = args
+        .info_names
+        .iter()
+        .cloned()
+        .chain(options.info_names)
+        .collect();
+    options.terminal_layers.clone_from(&args.terminal_layers);
+    options.terminal_blocks.clone_from(&args.terminal_blocks);
+    //the uuid namespace is left unset, every file gets its own which is worked out when it's converted
+
+    if !args.name_template.contains("{stem}") {
+        bail!("The name template must contain {{stem}}, otherwise every file would be written to the same .elmt");
+    }
+    if args.output.is_some() && args.file_names.len() > 1 {
+        bail!("--output only works when converting a single file, use --output-dir for multiple files");
+    }
+    if args.file_names.iter().any(|f| is_stdin(f)) && args.file_names.len() > 1 {
+        bail!("A .dxf from stdin can only be converted on its own");
+    }
+    let input_root = file_writer::common_root(&args.file_names);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or_default())
+        .build()
+        .context("Failed to create the conversion thread pool")?;
+
     // Load dxf file
     let dxf_loop_span = span!(Level::TRACE, "Looping over dxf files");
     let dxf_loop_guard = dxf_loop_span.enter();
-    for file_name in args.file_names {
-        let friendly_file_name = file_name
-            .file_stem()
-            .unwrap_or_else(|| file_name.as_os_str())
-            .to_string_lossy();
-        let drawing: Drawing = Drawing::load_file(&file_name).context(format!(
-            "Failed to load {friendly_file_name}...\n\tMake sure the file is a valid .dxf file.",
-        ))?;
-        let q_elmt = Definition::new(friendly_file_name.clone(), args.spline_step, &drawing);
-        if !args.verbose && args.info {
-            println!("{friendly_file_name} loaded...");
+    let results: Vec<FileReport> = pool.install(|| {
+        args.file_names
+            .par_iter()
+            .map(|file_name| {
+                let file_now = Instant::now();
+
+                //each file builds up its console output in a buffer, which then gets printed
+                //in one go, so the output from files being converted at the same time doesn't interleave
+                let (output, error, element, stats, incomplete) =
+                    match convert_file(file_name, &input_root, &args, &options) {
+                        std::result::Result::Ok((out, xml, out_file_name, element, stats)) => {
+                            if args.to_stdout() {
+                                eprint!("{out}");
+                            } else {
+                                print!("{out}");
+                            }
+                            if let Some(xml) = xml {
+                                print!("{xml}");
+                            }
+                            (
+                                Some(out_file_name),
+                                None,
+                                Some(element),
+                                Some(stats),
+                                Vec::new(),
+                            )
+                        }
+                        Err(e) => {
+                            eprintln!("{e:#}");
+                            //a file that failed in strict mode was still converted, so it has its
+                            //stats, along with the entities that made it fail
+                            let (stats, incomplete) = match e.downcast_ref::<dxf2elmt::Error>() {
+                                Some(dxf2elmt::Error::Incomplete { skipped, stats }) => {
+                                    (Some(stats.as_ref().clone()), skipped.clone())
+                                }
+                                _ => (None, Vec::new()),
+                            };
+                            (None, Some(format!("{e:#}")), None, stats, incomplete)
+                        }
+                    };
+
+                FileReport {
+                    input: file_name.clone(),
+                    output,
+                    error,
+                    elapsed_ms: file_now.elapsed().as_millis(),
+                    element,
+                    stats,
+                    incomplete,
+                }
+            })
+            .collect()
+    });
+    drop(dxf_loop_guard);
+
+    let converted: Vec<PathBuf> = results.iter().filter_map(|r| r.output.clone()).collect();
+    let failed = results.len() - converted.len();
+
+    if let Some(output_root) = args.output_dir.as_deref().filter(|_| args.collection) {
+        collection::write_categories(&converted, output_root, &input_root)
+            .context("Failed to write the element collection categories")?;
+    }
+
+    let summary = format!(
+        "Converted {} of {} files ({failed} failed) in {} ms",
+        converted.len(),
+        results.len(),
+        now.elapsed().as_millis()
+    );
+
+    //when the xml is going to stdout, keep the summary out of it
+    if args.to_stdout() {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+
+    if let Some(report_path) = &args.report {
+        BatchReport {
+            succeeded: converted.len(),
+            failed,
+            elapsed_ms: now.elapsed().as_millis(),
+            files: results,
         }
+        .write(report_path)?;
+    }
 
-        // Initialize counts
-        let mut circle_count: u32 = 0;
-        let mut line_count: u32 = 0;
-        let mut arc_count: u32 = 0;
-        let mut spline_count: u32 = 0;
-        let mut text_count: u32 = 0;
-        let mut ellipse_count: u32 = 0;
-        let mut polyline_count: u32 = 0;
-        let mut lwpolyline_count: u32 = 0;
-        let mut solid_count: u32 = 0;
-        let mut block_count: u32 = 0;
-        let mut other_count: u32 = 0;
-
-        // Loop through all entities, counting the element types
-        //drawing.entities().for_each(|e| match e.specific {
-        drawing.entities().for_each(|e| match e.specific {
-            EntityType::Circle(ref _circle) => {
-                circle_count += 1;
-            }
-            EntityType::Line(ref _line) => {
-                line_count += 1;
-            }
-            EntityType::Arc(ref _arc) => {
-                arc_count += 1;
-            }
-            EntityType::Spline(ref _spline) => {
-                spline_count += 1;
-            }
-            EntityType::Text(ref _text) => {
-                text_count += 1;
-            }
-            EntityType::Ellipse(ref _ellipse) => {
-                ellipse_count += 1;
-            }
-            EntityType::Polyline(ref _polyline) => {
-                polyline_count += 1;
-            }
-            EntityType::LwPolyline(ref _lwpolyline) => {
-                lwpolyline_count += 1;
-            }
-            EntityType::Solid(ref _solid) => {
-                solid_count += 1;
-            }
-            EntityType::Insert(ref _insert) => {
-                block_count += 1;
-            }
-            _ => {
-                other_count += 1;
-            }
-        });
+    if failed > 0 {
+        bail!("{failed} file(s) failed to convert");
+    }
+
+    Ok(())
+}
+
+//Converts a single file, returning its console output, and its xml when that's
+//going to stdout instead of a file
+#[allow(clippy::too_many_lines)]
+fn convert_file(
+    file_name: &Path,
+    input_root: &Path,
+    args: &Args,
+    options: &ConversionOptions,
+) -> Result<(
+    String,
+    Option<String>,
+    PathBuf,
+    ElementReport,
+    ConversionStats,
+)> {
+    let mut out = String::new();
 
+    // Start recording time
+    let now: Instant = Instant::now();
+
+    //a .dxf from stdin has no name, so name the element after the output file if there is one
+    let named_after = match &args.output {
+        Some(output) if is_stdin(file_name) && !is_stdin(output) => output,
+        _ => file_name,
+    };
+    let friendly_file_name = if is_stdin(named_after) {
+        "stdin".into()
+    } else {
+        named_after
+            .file_stem()
+            .unwrap_or(named_after.as_os_str())
+            .to_string_lossy()
+    };
+
+    //the .dxf is read in up front, so the deterministic uuids can be worked out from it
+    let data = if is_stdin(file_name) {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data).map(|_| data)
+    } else {
+        std::fs::read(file_name)
+    }
+    .context(format!("Failed to read {friendly_file_name}"))?;
+    let (drawing, hatches) = dxf2elmt::load(&mut io::Cursor::new(&data)).context(format!(
+        "Failed to load {friendly_file_name}...\n\tMake sure the file is a valid .dxf file.",
+    ))?;
+    //a .dxf from stdin isn't in a folder, so there are no config or names files to pick up
+    let mut options = if is_stdin(file_name) {
+        Cow::Borrowed(options)
+    } else {
+        config::options_for(file_name, options)?
+    };
+    let overrides = args.overrides();
+    if !overrides.is_empty() {
+        overrides.apply(options.to_mut());
+    }
+    if args.deterministic {
+        options.to_mut().uuid_namespace = Some(uuid_namespace(named_after, input_root, &data));
+    }
+    let q_elmt =
+        dxf2elmt::convert_with_hatches(friendly_file_name.clone(), &drawing, &hatches, &options)
+            .context(format!("Failed to convert {friendly_file_name}"))?;
+    if args.info {
+        writeln!(out, "{friendly_file_name} loaded...")?;
+    }
+
+    let (xml, out_file_name) = if args.to_stdout() {
+        (Some(q_elmt.to_string()), PathBuf::from("-"))
+    } else {
         // Create output file for .elmt
-        let out_file = file_writer::create_file(args.verbose, args.info, &file_name)?;
+        let out_file_name = args.output.clone().unwrap_or_else(|| {
+            file_writer::output_file_name(
+                file_name,
+                args.output_dir.as_deref(),
+                input_root,
+                &args.name_template,
+            )
+        });
+        let out_file = file_writer::create_file(&out_file_name)?;
+        writeln!(
+            out,
+            "{} was created... \nNow converting {}...",
+            out_file_name.display(),
+            if is_stdin(file_name) {
+                "stdin".into()
+            } else {
+                file_name.display().to_string()
+            }
+        )?;
 
         // Write to output file
-        let out_xml = XMLElement::from(&q_elmt);
-        out_xml
-            .write(&out_file)
+        q_elmt
+            .write_xml(&out_file)
             .context("Failed to write output file.")?;
 
-        if args.info {
-            println!("Conversion complete!\n");
-
-            // Print stats
-            println!("STATS");
-            println!("~~~~~~~~~~~~~~~");
-            println!("Circles: {circle_count}");
-            println!("Lines: {line_count}");
-            println!("Arcs: {arc_count}");
-            println!("Splines: {spline_count}");
-            println!("Texts: {text_count}");
-            println!("Ellipses: {ellipse_count}");
-            println!("Polylines: {polyline_count}");
-            println!("LwPolylines: {lwpolyline_count}");
-            println!("Solids: {solid_count}");
-            println!("Blocks: {block_count}");
-            println!("Currently Unsupported: {other_count}");
-
-            println!("\nTime Elapsed: {} ms", now.elapsed().as_millis());
-        }
+        (None, out_file_name)
+    };
 
-        if args.verbose {
-            print!("{out_xml}");
+    if args.info {
+        writeln!(out, "Conversion complete!\n")?;
+
+        // Print stats
+        let stats = q_elmt.stats();
+        writeln!(out, "STATS")?;
+        writeln!(out, "~~~~~~~~~~~~~~~")?;
+        for (ent_type, count) in &stats.entity_counts {
+            writeln!(out, "{ent_type}: {count}")?;
+        }
+        writeln!(out, "Currently Unsupported: {}", stats.unsupported.len())?;
+        for dropped in &stats.dropped {
+            writeln!(out, "Dropped: {dropped}")?;
+        }
+        for warning in &stats.warnings {
+            writeln!(out, "Warning: {warning}")?;
         }
+
+        writeln!(out, "\nTime Elapsed: {} ms", now.elapsed().as_millis())?;
     }
-    drop(dxf_loop_guard);
 
-    Ok(())
+    Ok((
+        out,
+        xml,
+        out_file_name,
+        ElementReport::from(&q_elmt),
+        q_elmt.stats().clone(),
+    ))
 }
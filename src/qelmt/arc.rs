@@ -1,6 +1,9 @@
 use crate::qelmt::Bounding;
 
-use super::{two_dec, ScaleEntity};
+use super::bulge::sweep;
+use super::polygon::Coordinate;
+use super::transform::{Matrix, Transform};
+use super::{ellipse, round_dec, ErrorKind, Polygon, ToXml};
 use dxf::entities;
 use simple_xml_builder::XMLElement;
 
@@ -55,13 +58,41 @@ impl From<&entities::Arc> for Arc {
     }
 }
 
-impl From<&Arc> for XMLElement {
-    fn from(arc: &Arc) -> Self {
+//An arc of an ellipse that isn't rotated, QET arcs can have a different width and height.
+//The ellipse parameters line up with QET's arc angles, once the direction of the major
+//axis is added on
+impl TryFrom<&entities::Ellipse> for Arc {
+    type Error = ErrorKind;
+
+    fn try_from(ellipse: &entities::Ellipse) -> Result<Self, Self::Error> {
+        let axis_angle = ellipse::axis_angle(ellipse).ok_or(ErrorKind::Rotated)?;
+        let (width, height) = ellipse::size(ellipse, axis_angle);
+        let start = ellipse.start_parameter.to_degrees() + axis_angle;
+        let angle = (ellipse.end_parameter - ellipse.start_parameter)
+            .to_degrees()
+            .rem_euclid(360.0);
+
+        Ok(Arc {
+            x: ellipse.center.x - width / 2.0,
+            y: -ellipse.center.y - height / 2.0,
+            width,
+            height,
+            start: start.rem_euclid(360.0),
+            angle: if angle == 0.0 { 360.0 } else { angle },
+            antialias: false,
+            style: "line-style:normal;line-weight:thin;filling:none;color:black".into(),
+        })
+    }
+}
+
+impl ToXml for Arc {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let arc = self;
         let mut arc_xml = XMLElement::new("arc");
-        arc_xml.add_attribute("x", two_dec(arc.x));
-        arc_xml.add_attribute("y", two_dec(arc.y));
-        arc_xml.add_attribute("width", two_dec(arc.width));
-        arc_xml.add_attribute("height", two_dec(arc.height));
+        arc_xml.add_attribute("x", round_dec(arc.x, precision));
+        arc_xml.add_attribute("y", round_dec(arc.y, precision));
+        arc_xml.add_attribute("width", round_dec(arc.width, precision));
+        arc_xml.add_attribute("height", round_dec(arc.height, precision));
         arc_xml.add_attribute("start", arc.start.round());
         arc_xml.add_attribute("angle", arc.angle.round());
         arc_xml.add_attribute("antialias", arc.antialias);
@@ -88,11 +119,59 @@ impl Bounding for Arc {
     }
 }
 
-impl ScaleEntity for Arc {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x *= fact_x;
-        self.y *= fact_y;
-        self.width *= fact_x;
-        self.height *= fact_y;
+impl Arc {
+    pub(super) fn is_circle(&self) -> bool {
+        (self.width - self.height).abs() < 1e-9
+    }
+}
+
+//Only for matrices that keep the arc's shape, anything else needs to be a polygon instead
+impl Transform for Arc {
+    fn transform(&mut self, matrix: &Matrix) {
+        let (rx, ry) = (self.width / 2.0, self.height / 2.0);
+        let (cx, cy) = matrix.apply(self.x + rx, self.y + ry);
+        let (half_width, half_height) = matrix.radii(rx, ry);
+
+        //where the ends of the arc end up, as angles on the new arc
+        let angle_of = |angle: f64| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let (x, y) = matrix.apply_vector(rx * cos, -ry * sin);
+            (-y / half_height).atan2(x / half_width).to_degrees()
+        };
+        let (start, end) = (angle_of(self.start), angle_of(self.start + self.angle));
+
+        //a mirrored arc runs the other way around, so it starts from the other end
+        let start = if matrix.is_mirrored() { end } else { start }.rem_euclid(360.0);
+
+        //keep the rounding errors from turning a start of 0 into -0 or 360
+        self.start = if start < 1e-9 || 360.0 - start < 1e-9 {
+            0.0
+        } else {
+            start
+        };
+        self.x = cx - half_width;
+        self.y = cy - half_height;
+        self.width = half_width * 2.0;
+        self.height = half_height * 2.0;
+    }
+}
+
+//the points along the arc, for when it's turned in a way a QET arc can't be
+impl From<&Arc> for Polygon {
+    fn from(arc: &Arc) -> Self {
+        let (rx, ry) = (arc.width / 2.0, arc.height / 2.0);
+        Polygon::with_style(
+            sweep(arc.start, arc.start + arc.angle, true)
+                .map(|angle| {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    Coordinate {
+                        x: arc.x + rx + rx * cos,
+                        y: arc.y + ry - ry * sin,
+                    }
+                })
+                .collect(),
+            false,
+            &arc.style,
+        )
     }
 }
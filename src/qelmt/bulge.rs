@@ -0,0 +1,297 @@
+use dxf::entities::{self, LwPolyline, Polyline};
+use dxf::Point;
+
+use super::{Arc, Line, Objects};
+
+//arcs are broken up into lines every few degrees when they are turned into polygon points
+pub(crate) const ARC_STEP: f64 = 5.0;
+
+//The arc a bulged polyline segment makes between two vertices. The bulge is the tan of a
+//quarter of the arc angle, a negative bulge goes clockwise
+pub(crate) struct BulgeArc {
+    pub center: Point,
+    pub radius: f64,
+
+    //the actual angles in degrees, going from start to end in the direction given by ccw
+    pub start: f64,
+    pub end: f64,
+    pub ccw: bool,
+}
+
+impl BulgeArc {
+    //None if the segment is just a straight line
+    pub fn new(p1: &Point, p2: &Point, bulge: f64) -> Option<Self> {
+        let chord = ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt();
+        if bulge.abs() < 1e-9 || chord < 1e-9 {
+            return None;
+        }
+
+        //the center is on the perpendicular through the middle of the chord
+        let theta = 4.0 * bulge.atan();
+        let to_center = chord / 2.0 / (theta / 2.0).tan();
+        let (dir_x, dir_y) = ((p2.x - p1.x) / chord, (p2.y - p1.y) / chord);
+        let center = Point::new(
+            (p1.x + p2.x) / 2.0 - dir_y * to_center,
+            (p1.y + p2.y) / 2.0 + dir_x * to_center,
+            0.0,
+        );
+        let angle_to = |pt: &Point| (pt.y - center.y).atan2(pt.x - center.x).to_degrees();
+
+        Some(BulgeArc {
+            radius: (chord / 2.0 / (theta / 2.0).sin()).abs(),
+            start: angle_to(p1),
+            end: angle_to(p2),
+            ccw: bulge > 0.0,
+            center,
+        })
+    }
+
+    //points every few degrees along the arc, including both ends
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        sweep(self.start, self.end, self.ccw).map(|angle| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            Point::new(
+                self.center.x + self.radius * cos,
+                self.center.y + self.radius * sin,
+                0.0,
+            )
+        })
+    }
+
+    //dxf arcs always go counter clockwise, so clockwise arcs are flipped around
+    pub fn to_arc(&self) -> entities::Arc {
+        let (start, end) = if self.ccw {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        };
+        entities::Arc::new(
+            self.center.clone(),
+            self.radius,
+            start.rem_euclid(360.0),
+            end.rem_euclid(360.0),
+        )
+    }
+}
+
+//the angles from start to end (both included) every few degrees, going in the given direction
+pub(crate) fn sweep(start: f64, end: f64, ccw: bool) -> impl Iterator<Item = f64> {
+    let mut angle = if ccw {
+        (end - start).rem_euclid(360.0)
+    } else {
+        -(start - end).rem_euclid(360.0)
+    };
+
+    //start and end being the same is a full circle, not an empty arc
+    if angle == 0.0 {
+        angle = if ccw { 360.0 } else { -360.0 };
+    }
+
+    //at most a full turn, so there aren't many steps
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let steps = (angle.abs() / ARC_STEP).ceil().max(1.0) as u32;
+    (0..=steps).map(move |i| start + angle * f64::from(i) / f64::from(steps))
+}
+
+//Polylines and LwPolylines both have a bulge on every vertex, for the segment
+//going from that vertex to the next one
+pub(crate) trait Bulges {
+    fn bulge_vertices(&self) -> Vec<(Point, f64)>;
+    fn closed(&self) -> bool;
+
+    fn has_bulges(&self) -> bool {
+        self.segments()
+            .iter()
+            .any(|(_, _, bulge)| bulge.abs() >= 1e-9)
+    }
+
+    //each segment between two vertices along with its bulge, including the
+    //segment back to the first vertex if the polyline is closed
+    fn segments(&self) -> Vec<(Point, Point, f64)> {
+        let vertices = self.bulge_vertices();
+        let count = if self.closed() {
+            vertices.len()
+        } else {
+            vertices.len().saturating_sub(1)
+        };
+
+        vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .take(count)
+            .map(|((p1, bulge), (p2, _))| (p1.clone(), p2.clone(), *bulge))
+            .collect()
+    }
+
+    //the vertices, with extra points along the arcs of the bulged segments
+    fn outline(&self) -> Vec<Point> {
+        let segments = self.segments();
+        let mut points: Vec<Point> = self
+            .bulge_vertices()
+            .into_iter()
+            .take(1)
+            .map(|(pt, _)| pt)
+            .collect();
+
+        for (p1, p2, bulge) in &segments {
+            match BulgeArc::new(p1, p2, *bulge) {
+                Some(arc) => points.extend(arc.points().skip(1)),
+                None => points.push(p2.clone()),
+            }
+        }
+
+        //the last segment of a closed polyline ends back on the first vertex
+        if self.closed() && points.len() > 1 {
+            points.pop();
+        }
+        points
+    }
+
+    //the segments as QET lines, and arcs for the bulged ones
+    fn lines_and_arcs(&self) -> Vec<Objects> {
+        self.segments()
+            .iter()
+            .map(|(p1, p2, bulge)| match BulgeArc::new(p1, p2, *bulge) {
+                Some(arc) => Objects::Arc(Arc::from(&arc.to_arc())),
+                None => Objects::Line(Line::from(&entities::Line::new(p1.clone(), p2.clone()))),
+            })
+            .collect()
+    }
+}
+
+impl Bulges for Polyline {
+    fn bulge_vertices(&self) -> Vec<(Point, f64)> {
+        self.vertices()
+            .map(|vtx| (vtx.location.clone(), vtx.bulge))
+            .collect()
+    }
+
+    fn closed(&self) -> bool {
+        self.is_closed()
+    }
+}
+
+impl Bulges for LwPolyline {
+    fn bulge_vertices(&self) -> Vec<(Point, f64)> {
+        self.vertices
+            .iter()
+            .map(|vtx| (Point::new(vtx.x, vtx.y, 0.0), vtx.bulge))
+            .collect()
+    }
+
+    fn closed(&self) -> bool {
+        self.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dxf::LwPolylineVertex;
+
+    fn assert_point(actual: &Point, x: f64, y: f64) {
+        assert!(
+            (actual.x - x).abs() < 1e-9 && (actual.y - y).abs() < 1e-9,
+            "({}, {}) != ({x}, {y})",
+            actual.x,
+            actual.y
+        );
+    }
+
+    fn lw_polyline(vertices: &[(f64, f64, f64)], closed: bool) -> LwPolyline {
+        let mut poly = LwPolyline {
+            vertices: vertices
+                .iter()
+                .map(|&(x, y, bulge)| LwPolylineVertex {
+                    x,
+                    y,
+                    bulge,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        poly.set_is_closed(closed);
+        poly
+    }
+
+    #[test]
+    fn zero_bulge_is_a_straight_line() {
+        let (p1, p2) = (Point::origin(), Point::new(2.0, 0.0, 0.0));
+        assert!(BulgeArc::new(&p1, &p2, 0.0).is_none());
+        //and so is a segment that doesn't go anywhere
+        assert!(BulgeArc::new(&p1, &p1, 1.0).is_none());
+    }
+
+    #[test]
+    fn positive_bulge_goes_counter_clockwise() {
+        //a bulge of 1 is a half circle, to the right of the direction of the segment
+        let arc = BulgeArc::new(&Point::origin(), &Point::new(2.0, 0.0, 0.0), 1.0).unwrap();
+        assert_point(&arc.center, 1.0, 0.0);
+        assert!((arc.radius - 1.0).abs() < 1e-9);
+        assert!(arc.ccw);
+
+        let points: Vec<_> = arc.points().collect();
+        assert_eq!(points.len(), 37);
+        assert_point(&points[0], 0.0, 0.0);
+        assert_point(&points[18], 1.0, -1.0);
+        assert_point(&points[36], 2.0, 0.0);
+    }
+
+    #[test]
+    fn negative_bulge_goes_clockwise() {
+        let arc = BulgeArc::new(&Point::origin(), &Point::new(2.0, 0.0, 0.0), -1.0).unwrap();
+        assert!(!arc.ccw);
+        let points: Vec<_> = arc.points().collect();
+        assert_point(&points[18], 1.0, 1.0);
+
+        //which a dxf arc draws counter clockwise from the end to the start
+        let dxf_arc = arc.to_arc();
+        assert!((dxf_arc.start_angle - 0.0).abs() < 1e-9);
+        assert!((dxf_arc.end_angle - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quarter_circle() {
+        let bulge = (22.5_f64).to_radians().tan();
+        let arc = BulgeArc::new(
+            &Point::new(1.0, 0.0, 0.0),
+            &Point::new(0.0, 1.0, 0.0),
+            bulge,
+        )
+        .unwrap();
+        assert_point(&arc.center, 0.0, 0.0);
+        assert!((arc.radius - 1.0).abs() < 1e-9);
+        assert!((arc.start - 0.0).abs() < 1e-9 && (arc.end - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sweeps() {
+        let angles: Vec<_> = sweep(350.0, 10.0, true).collect();
+        assert_eq!(angles.len(), 5);
+        assert!((angles[4] - 370.0).abs() < 1e-9);
+
+        let angles: Vec<_> = sweep(10.0, 350.0, false).collect();
+        assert!((angles[4] + 10.0).abs() < 1e-9);
+
+        //the same start and end goes all the way around
+        assert_eq!(sweep(90.0, 90.0, true).count(), 73);
+    }
+
+    #[test]
+    fn closed_outline() {
+        //two half circles make a full one, which doesn't repeat the first vertex at the end
+        let poly = lw_polyline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 1.0)], true);
+        assert!(poly.has_bulges());
+        assert_eq!(poly.segments().len(), 2);
+        let outline = poly.outline();
+        assert_eq!(outline.len(), 72);
+        assert_point(&outline[0], 0.0, 0.0);
+        assert_point(&outline[36], 2.0, 0.0);
+
+        let poly = lw_polyline(&[(0.0, 0.0, 0.0), (2.0, 0.0, 0.0)], false);
+        assert!(!poly.has_bulges());
+        assert_eq!(poly.outline().len(), 2);
+        assert_eq!(poly.lines_and_arcs().len(), 1);
+    }
+}
@@ -0,0 +1,252 @@
+use dxf::entities::{
+    self, AngularThreePointDimension, DiameterDimension, DimensionBase, EntityType,
+    OrdinateDimension, RadialDimension, RotatedDimension,
+};
+use dxf::enums::{DimensionType, HorizontalTextJustification, VerticalTextJustification};
+use dxf::tables::DimStyle;
+use dxf::Point;
+use hex_color::HexColor;
+
+use super::dynamictext::DTextBuilder;
+use super::{mtext, Arc, ErrorKind, Line, Objects};
+
+//All of the dxf dimension types share the DimensionBase, but the points that
+//define where the dimension goes mean something different for each of them
+pub(crate) enum Dimension<'a> {
+    Linear(&'a RotatedDimension),
+    Radial(&'a RadialDimension),
+    Diameter(&'a DiameterDimension),
+    Angular(&'a AngularThreePointDimension),
+    Ordinate(&'a OrdinateDimension),
+}
+
+impl<'a> TryFrom<&'a EntityType> for Dimension<'a> {
+    type Error = ErrorKind;
+
+    fn try_from(ent: &'a EntityType) -> Result<Self, Self::Error> {
+        match ent {
+            EntityType::RotatedDimension(dim) => Ok(Dimension::Linear(dim)),
+            EntityType::RadialDimension(dim) => Ok(Dimension::Radial(dim)),
+            EntityType::DiameterDimension(dim) => Ok(Dimension::Diameter(dim)),
+            EntityType::AngularThreePointDimension(dim) => Ok(Dimension::Angular(dim)),
+            EntityType::OrdinateDimension(dim) => Ok(Dimension::Ordinate(dim)),
+            _ => Err(ErrorKind::Unsupported),
+        }
+    }
+}
+
+//The parts of the dimension style needed to draw a dimension ourselves
+pub(crate) struct Style {
+    text_height: f64,
+    arrow_size: f64,
+    decimals: usize,
+}
+
+impl From<Option<&&DimStyle>> for Style {
+    fn from(style: Option<&&DimStyle>) -> Self {
+        match style {
+            Some(style) => Style {
+                text_height: style.dimensioning_text_height,
+                arrow_size: style.dimensioning_arrow_size,
+                decimals: usize::try_from(style.dimension_unit_tolerance_decimal_places)
+                    .unwrap_or_default(),
+            },
+            //the metric defaults, since the elements end up in mm anyway
+            None => Style {
+                text_height: 2.5,
+                arrow_size: 2.5,
+                decimals: 2,
+            },
+        }
+    }
+}
+
+impl Dimension<'_> {
+    pub fn base(&self) -> &DimensionBase {
+        match self {
+            Dimension::Linear(dim) => &dim.dimension_base,
+            Dimension::Radial(dim) => &dim.dimension_base,
+            Dimension::Diameter(dim) => &dim.dimension_base,
+            Dimension::Angular(dim) => &dim.dimension_base,
+            Dimension::Ordinate(dim) => &dim.dimension_base,
+        }
+    }
+
+    //Draws the dimension from its definition points, for when the block with the dimension
+    //graphics is missing. Everything is still in dxf coordinates, scaling and offsetting
+    //is left up to the caller like it is for any other entity
+    #[allow(clippy::too_many_lines)]
+    pub fn draw(&self, style: &Style, color: HexColor) -> Vec<Objects> {
+        let base = self.base();
+        let mut objects = Vec::new();
+
+        let (measurement, prefix, suffix, direction) = match self {
+            Dimension::Linear(dim) => {
+                let (p2, p3) = (&dim.definition_point_2, &dim.definition_point_3);
+
+                //aligned dimensions run parallel to the measured points, the rest are rotated
+                let direction = if base.dimension_type == DimensionType::Aligned {
+                    let len = distance(p2, p3);
+                    if len > 0.0 {
+                        ((p3.x - p2.x) / len, (p3.y - p2.y) / len)
+                    } else {
+                        (1.0, 0.0)
+                    }
+                } else {
+                    let (sin, cos) = dim.rotation_angle.to_radians().sin_cos();
+                    (cos, sin)
+                };
+
+                //the dimension line goes through definition point 1, the extension lines run from
+                //the measured points to where they meet the dimension line
+                let onto_dim_line = |pt: &Point| {
+                    let along = (pt.x - base.definition_point_1.x) * direction.0
+                        + (pt.y - base.definition_point_1.y) * direction.1;
+                    Point::new(
+                        base.definition_point_1.x + direction.0 * along,
+                        base.definition_point_1.y + direction.1 * along,
+                        0.0,
+                    )
+                };
+                let (q2, q3) = (onto_dim_line(p2), onto_dim_line(p3));
+
+                objects.push(line(p2, &q2));
+                objects.push(line(p3, &q3));
+                objects.push(arrowed_line(&q2, &q3, true, true, style));
+
+                (distance(&q2, &q3), "", "", direction)
+            }
+            Dimension::Radial(dim) => {
+                let (center, on_curve) = (&base.definition_point_1, &dim.definition_point_2);
+                objects.push(arrowed_line(center, on_curve, false, true, style));
+
+                (distance(center, on_curve), "R", "", (1.0, 0.0))
+            }
+            Dimension::Diameter(dim) => {
+                let (p1, p2) = (&base.definition_point_1, &dim.definition_point_2);
+                objects.push(arrowed_line(p1, p2, true, true, style));
+
+                (distance(p1, p2), "⌀", "", (1.0, 0.0))
+            }
+            Dimension::Angular(dim) => {
+                let vertex = &dim.definition_point_4;
+                let radius = distance(vertex, &base.definition_point_1);
+                let angle_to = |pt: &Point| {
+                    (pt.y - vertex.y)
+                        .atan2(pt.x - vertex.x)
+                        .to_degrees()
+                        .rem_euclid(360.0)
+                };
+                let (start, end) = (
+                    angle_to(&dim.definition_point_2),
+                    angle_to(&dim.definition_point_3),
+                );
+
+                //dxf arcs always go counter clockwise, so start from whichever side
+                //gets the arc to pass through the point the dimension arc was placed at
+                let arc_at = angle_to(&base.definition_point_1);
+                let (start, end) =
+                    if (arc_at - start).rem_euclid(360.0) <= (end - start).rem_euclid(360.0) {
+                        (start, end)
+                    } else {
+                        (end, start)
+                    };
+                let on_arc = |angle: f64| {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    Point::new(vertex.x + radius * cos, vertex.y + radius * sin, 0.0)
+                };
+
+                objects.push(Objects::Arc(Arc::from(&entities::Arc::new(
+                    vertex.clone(),
+                    radius,
+                    start,
+                    end,
+                ))));
+                objects.push(line(
+                    &dim.definition_point_2,
+                    &on_arc(angle_to(&dim.definition_point_2)),
+                ));
+                objects.push(line(
+                    &dim.definition_point_3,
+                    &on_arc(angle_to(&dim.definition_point_3)),
+                ));
+
+                ((end - start).rem_euclid(360.0), "", "°", (1.0, 0.0))
+            }
+            Dimension::Ordinate(dim) => {
+                objects.push(line(&dim.definition_point_2, &dim.definition_point_3));
+
+                //measured from the origin in definition point 1
+                let measurement = if base.is_ordinate_x_type {
+                    dim.definition_point_2.x - base.definition_point_1.x
+                } else {
+                    dim.definition_point_2.y - base.definition_point_1.y
+                };
+                (measurement.abs(), "", "", (1.0, 0.0))
+            }
+        };
+
+        //the actual measurement isn't always written out, so fall back to the one worked out above.
+        //Angles are written out in radians, but shown in degrees like the one worked out above
+        let measurement = match self {
+            _ if base.actual_measurement == 0.0 => measurement,
+            Dimension::Angular(_) => base.actual_measurement.to_degrees(),
+            _ => base.actual_measurement,
+        };
+        let value = format!("{prefix}{measurement:.*}{suffix}", style.decimals);
+
+        //<> in the dimension text is replaced with the measurement, a single space hides the text
+        let text = match base.text.as_str() {
+            "" | "<>" => value,
+            " " => String::new(),
+            text => text.replace("<>", &value),
+        };
+
+        if !text.is_empty() {
+            let rotation = if base.text_rotation_angle == 0.0 {
+                //keep the text readable, instead of upside down
+                let angle = direction.1.atan2(direction.0).to_degrees();
+                if angle > 90.0 || angle <= -90.0 {
+                    angle + 180.0
+                } else {
+                    angle
+                }
+            } else {
+                base.text_rotation_angle
+            };
+
+            let dim_text = entities::Text {
+                location: base.text_mid_point.clone(),
+                text_height: style.text_height,
+                value: mtext::plain_text(&mtext::parse_str(&text, style.text_height)),
+                rotation,
+                horizontal_text_justification: HorizontalTextJustification::Center,
+                vertical_text_justification: VerticalTextJustification::Middle,
+                ..Default::default()
+            };
+            objects.push(Objects::DynamicText(
+                DTextBuilder::from_text(&dim_text).color(color).build(),
+            ));
+        }
+
+        objects
+    }
+}
+
+fn distance(p1: &Point, p2: &Point) -> f64 {
+    ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
+}
+
+fn line(p1: &Point, p2: &Point) -> Objects {
+    Objects::Line(Line::from(&entities::Line::new(p1.clone(), p2.clone())))
+}
+
+fn arrowed_line(p1: &Point, p2: &Point, arrow1: bool, arrow2: bool, style: &Style) -> Objects {
+    Objects::Line(
+        Line::from(&entities::Line::new(p1.clone(), p2.clone())).arrows(
+            arrow1,
+            arrow2,
+            style.arrow_size,
+        ),
+    )
+}
@@ -1,7 +1,9 @@
 use crate::qelmt::Bounding;
 
-use super::{two_dec, FontInfo, ScaleEntity, TextEntity};
-use dxf::entities::{self, AttributeDefinition};
+use super::mtext::Piece;
+use super::transform::{Matrix, Transform};
+use super::{mtext_value, round_dec, FontInfo, TextEntity, ToXml};
+use dxf::entities::{self, Attribute, AttributeDefinition};
 use hex_color::HexColor;
 use simple_xml_builder::XMLElement;
 use unicode_segmentation::UnicodeSegmentation;
@@ -34,8 +36,56 @@ pub struct DynamicText {
     reference_rectangle_width: f64,
 }
 
-impl From<&DynamicText> for XMLElement {
-    fn from(txt: &DynamicText) -> Self {
+impl DynamicText {
+    //swaps in one of the pieces an MTEXT got split into, along with its formatting
+    //when the MTEXT was split up, the pieces have already been lined up so they're left aligned
+    pub(crate) fn apply_mtext_piece(&mut self, piece: &Piece, split: bool) {
+        self.text.clone_from(&piece.text);
+        self.x += piece.dx;
+        self.y += piece.dy;
+        piece.format.apply(&mut self.font, &mut self.color);
+
+        if split {
+            self.h_alignment = HAlignment::Left;
+            self.reference_rectangle_width = 0.0;
+        }
+    }
+
+    //o.k. ... as long as we do not know the real width:
+    //"guess" the width by number of characters and font-size:
+    #[allow(clippy::cast_precision_loss)]
+    fn width(&self) -> f64 {
+        if self.reference_rectangle_width > 2.0 {
+            self.reference_rectangle_width
+        } else {
+            (self.text.graphemes(true).count() as f64) * self.font.point_size * 0.75
+        }
+    }
+
+    //we need the horizontal alignment and the text-width to get to the left side of the text
+    fn left(&self) -> f64 {
+        match self.h_alignment {
+            HAlignment::Left => self.x,
+            HAlignment::Center => self.x - self.width() / 2.0,
+            HAlignment::Right => self.x - self.width(),
+        }
+    }
+
+    pub(crate) fn set_uuid(&mut self, uuid: Uuid) {
+        self.uuid = uuid;
+    }
+
+    //the element information this text shows, and the value it was given in the dxf
+    pub(crate) fn element_info(&self) -> Option<(&str, &str)> {
+        self.info_name
+            .as_deref()
+            .map(|info_name| (info_name, self.text.as_str()))
+    }
+}
+
+impl ToXml for DynamicText {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let txt = self;
         let mut dtxt_xml = XMLElement::new("dynamic_text");
         // taken from QET_ElementScaler: "ElmtDynText::AsSVGstring"
         //    // Position und Rotationspunkt berechnen:
@@ -57,31 +107,15 @@ impl From<&DynamicText> for XMLElement {
         //
         //
         // it's just annoying if the value for "reference_rectangle_width" in the dxf is “0.0”...
+        // so width() guesses it
         //
-        // o.k. ... as long as we do not know the real width:
-        // "guess" the width by number of characters and font-size:
-        //
-        let graphene_count = txt.text.graphemes(true).count();
-        let txt_width = if txt.reference_rectangle_width > 2.0 {
-            txt.reference_rectangle_width
-        } else {
-            (graphene_count as f64) * pt_size * 0.75
-        };
-
-        let x_pos = {
-            let x_pos = txt.x + 0.5 - (pt_size / 8.0) - 4.05;
-            match txt.h_alignment {
-                HAlignment::Left => x_pos,
-                HAlignment::Center => x_pos - txt_width / 2.0,
-                HAlignment::Right => x_pos - txt_width,
-            }
-        };
+        let x_pos = txt.left() + 0.5 - (pt_size / 8.0) - 4.05;
         let y_pos = txt.y + 0.5 - (7.0 / 5.0 * pt_size + 26.0 / 5.0) + pt_size;
 
-        dtxt_xml.add_attribute("x", two_dec(x_pos));
-        dtxt_xml.add_attribute("y", two_dec(y_pos));
-        dtxt_xml.add_attribute("z", two_dec(txt.z));
-        dtxt_xml.add_attribute("rotation", two_dec(txt.rotation));
+        dtxt_xml.add_attribute("x", round_dec(x_pos, precision));
+        dtxt_xml.add_attribute("y", round_dec(y_pos, precision));
+        dtxt_xml.add_attribute("z", round_dec(txt.z, precision));
+        dtxt_xml.add_attribute("rotation", round_dec(txt.rotation, precision));
         dtxt_xml.add_attribute("uuid", format!("{{{}}}", txt.uuid));
         dtxt_xml.add_attribute("font", &txt.font);
         dtxt_xml.add_attribute("Halignment", &txt.h_alignment);
@@ -91,17 +125,17 @@ impl From<&DynamicText> for XMLElement {
         dtxt_xml.add_attribute("text_width", txt.text_width);
         dtxt_xml.add_attribute("color", txt.color.display_rgb());
 
-        //If I ever add support for other text_from types, element and composite text
-        //I'll need to add more smarts here, as there may be some other children components
-        //for now since it only supports user_text I'm just statically adding the single child
-        //component needed
-        //match txt.text_from
+        //If I ever add support for composite text I'll need to add more smarts here, as
+        //there may be some other children components. A user text only needs the text, and
+        //an element info text also names the information it shows
         let mut text_xml = XMLElement::new("text");
         text_xml.add_text(&txt.text);
         dtxt_xml.add_child(text_xml);
 
         if let Some(i_name) = &txt.info_name {
-            dtxt_xml.add_attribute("info_name", i_name);
+            let mut info_xml = XMLElement::new("info_name");
+            info_xml.add_text(i_name);
+            dtxt_xml.add_child(info_xml);
         }
 
         if txt.keep_visual_rotation {
@@ -114,36 +148,36 @@ impl From<&DynamicText> for XMLElement {
 
 impl Bounding for DynamicText {
     fn left_bound(&self) -> f64 {
-        self.x
+        self.left()
     }
 
     fn right_bound(&self) -> f64 {
-        //todo!()
-        1.0
+        self.left() + self.width()
     }
 
     fn top_bound(&self) -> f64 {
-        self.y
+        //the y position is the baseline, so the text sits above it
+        self.y - self.font.point_size
     }
 
     fn bot_bound(&self) -> f64 {
-        //todo!()
-        1.0
+        self.y
     }
 }
 
-impl ScaleEntity for DynamicText {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x *= fact_x;
-        self.y *= fact_y;
+impl Transform for DynamicText {
+    fn transform(&mut self, matrix: &Matrix) {
+        (self.x, self.y) = matrix.apply(self.x, self.y);
+        self.rotation += matrix.text_rotation(self.rotation);
         //self.font.pixel_size *= fact;
-        self.font.point_size *= fact_x;
+        self.font.point_size *= matrix.scale_x();
     }
 }
 
 pub struct DTextBuilder<'a> {
     text: TextEntity<'a>,
     color: Option<HexColor>,
+    info_name: Option<String>,
 }
 
 impl<'a> DTextBuilder<'a> {
@@ -151,6 +185,7 @@ impl<'a> DTextBuilder<'a> {
         Self {
             text: TextEntity::Text(text),
             color: None,
+            info_name: None,
         }
     }
 
@@ -158,6 +193,7 @@ impl<'a> DTextBuilder<'a> {
         Self {
             text: TextEntity::MText(text),
             color: None,
+            info_name: None,
         }
     }
 
@@ -165,6 +201,15 @@ impl<'a> DTextBuilder<'a> {
         Self {
             text: TextEntity::Attrib(attrib),
             color: None,
+            info_name: None,
+        }
+    }
+
+    pub fn from_attribute(attrib: &'a Attribute) -> Self {
+        Self {
+            text: TextEntity::Attribute(attrib),
+            color: None,
+            info_name: None,
         }
     }
 
@@ -175,6 +220,14 @@ impl<'a> DTextBuilder<'a> {
         }
     }
 
+    //shows one of the element informations instead of a fixed text
+    pub fn info_name(self, info_name: Option<&str>) -> Self {
+        Self {
+            info_name: info_name.map(Into::into),
+            ..self
+        }
+    }
+
     pub fn build(self) -> DynamicText {
         let (
             x,
@@ -225,11 +278,7 @@ impl<'a> DTextBuilder<'a> {
                 //and Code point 1 the normal text value says: "Text string. If the text string is less than 250 characters, all characters appear
                 //in group 1. If the text string is greater than 250 characters, the string is divided into 250-character chunks, which appear in
                 //one or more group 3 codes. If group 3 codes are used, the last group is a group 1 and has fewer than 250 characters"
-                {
-                    let mut val = mtxt.extended_text.join("");
-                    val.push_str(&mtxt.text);
-                    val.replace("\\P", "\n")
-                },
+                mtext_value(mtxt),
                 HAlignment::from(mtxt.attachment_point),
                 VAlignment::from(mtxt.attachment_point),
                 mtxt.reference_rectangle_width,
@@ -246,6 +295,18 @@ impl<'a> DTextBuilder<'a> {
                 VAlignment::from(attrib.vertical_text_justification),
                 0.0, // as Placeholder: not need to check if Attrib has something similar
             ),
+            TextEntity::Attribute(attrib) => (
+                attrib.location.x,
+                -attrib.location.y,
+                attrib.location.z,
+                attrib.rotation,
+                &attrib.text_style_name,
+                attrib.text_height,
+                attrib.value.clone(),
+                HAlignment::from(attrib.horizontal_text_justification),
+                VAlignment::from(attrib.vertical_text_justification),
+                0.0,
+            ),
         };
 
         // Create a FontContext (font database) and LayoutContext (scratch space).
@@ -278,16 +339,18 @@ impl<'a> DTextBuilder<'a> {
         /*dbg!(&value);
         dbg!(&y);
         dbg!(&self.text);*/
+        #[allow(clippy::cast_possible_truncation)]
+        let rotation = if rotation.abs().round() as i64 % 360 != 0 {
+            rotation - 180.0
+        } else {
+            0.0
+        };
         DynamicText {
             //x: x - (calc_width as f64/2.0),
             x,
             y,
             z,
-            rotation: if rotation.abs().round() as i64 % 360 != 0 {
-                rotation - 180.0
-            } else {
-                0.0
-            },
+            rotation,
             uuid: Uuid::new_v4(),
             font: if style_name == "STANDARD" {
                 FontInfo {
@@ -306,14 +369,18 @@ impl<'a> DTextBuilder<'a> {
             reference_rectangle_width, //liest aus der dxf-Datei!!!
             h_alignment,
             v_alignment,
-            text_from: "UserText".into(),
+            text_from: if self.info_name.is_some() {
+                "ElementInfo".into()
+            } else {
+                "UserText".into()
+            },
             frame: false,
             text_width: -1,
             color: self.color.unwrap_or(HexColor::BLACK),
 
             text: value,
             keep_visual_rotation: false,
-            info_name: None,
+            info_name: self.info_name,
         }
     }
 }
@@ -1,6 +1,10 @@
-use super::{two_dec, Bounding, Circularity, ScaleEntity};
+use super::bulge::sweep;
+use super::polygon::Coordinate;
+use super::transform::{Matrix, Transform};
+use super::{round_dec, Bounding, Circularity, ErrorKind, Polygon, ToXml};
 use dxf::entities::{self, Circle, LwPolyline, Polyline};
 use simple_xml_builder::XMLElement;
+use std::f64::consts::PI;
 
 #[derive(Debug)]
 pub struct Ellipse {
@@ -36,51 +40,76 @@ impl From<&Circle> for Ellipse {
     }
 }
 
-impl From<&entities::Ellipse> for Ellipse {
-    fn from(ellipse: &entities::Ellipse) -> Self {
-        Ellipse {
-            x: ellipse.center.x - ellipse.major_axis.x,
-            y: -ellipse.center.y - ellipse.major_axis.x * ellipse.minor_axis_ratio,
-            height: ellipse.major_axis.x * 2.0,
-            width: ellipse.major_axis.x * 2.0 * ellipse.minor_axis_ratio,
+//QET ellipses and arcs can't be rotated, so they only work for ellipses with their major
+//axis along x or y. This is the direction of the major axis in degrees (0, 90, 180 or 270),
+//or None if the ellipse is rotated any other way
+pub(super) fn axis_angle(ellipse: &entities::Ellipse) -> Option<f64> {
+    let angle = ellipse
+        .major_axis
+        .y
+        .atan2(ellipse.major_axis.x)
+        .to_degrees()
+        .rem_euclid(360.0);
+    let nearest = (angle / 90.0).round() * 90.0;
+    ((angle - nearest).abs() < 1e-6).then_some(nearest.rem_euclid(360.0))
+}
+
+//the start and end parameters cover the whole ellipse, instead of just an arc of it
+pub(super) fn is_full(ellipse: &entities::Ellipse) -> bool {
+    let sweep = (ellipse.end_parameter - ellipse.start_parameter).rem_euclid(2.0 * PI);
+    sweep < 1e-6 || 2.0 * PI - sweep < 1e-6
+}
+
+//the width and height of an axis aligned ellipse
+#[allow(clippy::cast_possible_truncation)]
+pub(super) fn size(ellipse: &entities::Ellipse, axis_angle: f64) -> (f64, f64) {
+    let major = ellipse.major_axis.x.hypot(ellipse.major_axis.y) * 2.0;
+    let minor = major * ellipse.minor_axis_ratio;
+    //the axis angle is always a multiple of 90
+    if (axis_angle / 90.0).round() as i64 % 2 == 0 {
+        (major, minor)
+    } else {
+        (minor, major)
+    }
+}
+
+//only full ellipses that aren't rotated can be a QET ellipse
+impl TryFrom<&entities::Ellipse> for Ellipse {
+    type Error = ErrorKind;
+
+    fn try_from(ellipse: &entities::Ellipse) -> Result<Self, Self::Error> {
+        let axis_angle = axis_angle(ellipse).ok_or(ErrorKind::Rotated)?;
+        if !is_full(ellipse) {
+            return Err(ErrorKind::PartialEllipse);
+        }
+
+        let (width, height) = size(ellipse, axis_angle);
+        Ok(Ellipse {
+            x: ellipse.center.x - width / 2.0,
+            y: -ellipse.center.y - height / 2.0,
+            height,
+            width,
 
             //in the original code antialias is always set to false...I'm guessing for performance
             //reasons...I'm trying to think if there is a time we might want to turn it on?
             antialias: false,
             style: "line-style:normal;line-weight:thin;filling:none;color:black".into(),
-        }
+        })
     }
 }
 
-impl TryFrom<&Polyline> for Ellipse {
-    type Error = &'static str; //add better error later
+impl TryFrom<(&Polyline, f64)> for Ellipse {
+    type Error = ErrorKind;
 
-    fn try_from(poly: &Polyline) -> Result<Self, Self::Error> {
-        if !poly.is_circular() {
-            return Err("Polyline has poor circularity, can't convert");
+    fn try_from((poly, tolerance): (&Polyline, f64)) -> Result<Self, Self::Error> {
+        if !poly.is_circular(tolerance) {
+            return Err(ErrorKind::NotCircular);
         }
 
-        //I did this fold because min requires the vertex to have the Ordering trait
-        //but I forogot min_by exists taking a lambda, so I could compare them using
-        //the value I need. However my first quick attempt wasn't working
-        //Using min_by would probably be more effecietn than the fold
-        //So this is probably worth coming back to...but it's a low priority
-        //because the below code works.
-        let x = poly
-            .vertices()
-            .fold(f64::MAX, |min_x, vtx| min_x.min(vtx.location.x));
-
-        let max_x = poly
-            .vertices()
-            .fold(f64::MIN, |max_x, vtx| max_x.max(vtx.location.x));
-
-        let y = poly
-            .vertices()
-            .fold(f64::MAX, |min_y, vtx| min_y.min(vtx.location.y));
-
-        let max_y = poly
-            .vertices()
-            .fold(f64::MIN, |max_y, vtx| max_y.max(vtx.location.y));
+        //the bounds include the arcs of any bulged segments, a circle drawn as a polyline
+        //is often just two vertices with half circle bulges between them
+        let (x, max_x) = (poly.left_bound(), poly.right_bound());
+        let (y, max_y) = (poly.bot_bound(), poly.top_bound());
 
         Ok(Ellipse {
             x,
@@ -96,33 +125,16 @@ impl TryFrom<&Polyline> for Ellipse {
     }
 }
 
-impl TryFrom<&LwPolyline> for Ellipse {
-    type Error = &'static str; //add better error later
+impl TryFrom<(&LwPolyline, f64)> for Ellipse {
+    type Error = ErrorKind;
 
-    fn try_from(poly: &LwPolyline) -> Result<Self, Self::Error> {
-        if !poly.is_circular() {
-            return Err("Polyline has poor circularity, can't convert");
+    fn try_from((poly, tolerance): (&LwPolyline, f64)) -> Result<Self, Self::Error> {
+        if !poly.is_circular(tolerance) {
+            return Err(ErrorKind::NotCircular);
         }
 
-        let x = poly
-            .vertices
-            .iter()
-            .fold(f64::MAX, |min_x, vtx| min_x.min(vtx.x));
-
-        let max_x = poly
-            .vertices
-            .iter()
-            .fold(f64::MIN, |max_x, vtx| max_x.max(vtx.x));
-
-        let y = poly
-            .vertices
-            .iter()
-            .fold(f64::MAX, |min_y, vtx| min_y.min(vtx.y));
-
-        let max_y = poly
-            .vertices
-            .iter()
-            .fold(f64::MIN, |max_y, vtx| max_y.max(vtx.y));
+        let (x, max_x) = (poly.left_bound(), poly.right_bound());
+        let (y, max_y) = (poly.bot_bound(), poly.top_bound());
 
         Ok(Ellipse {
             x,
@@ -138,13 +150,14 @@ impl TryFrom<&LwPolyline> for Ellipse {
     }
 }
 
-impl From<&Ellipse> for XMLElement {
-    fn from(ell: &Ellipse) -> Self {
+impl ToXml for Ellipse {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let ell = self;
         let mut ell_xml = XMLElement::new("ellipse");
-        ell_xml.add_attribute("x", two_dec(ell.x));
-        ell_xml.add_attribute("y", two_dec(ell.y));
-        ell_xml.add_attribute("width", two_dec(ell.width));
-        ell_xml.add_attribute("height", two_dec(ell.height));
+        ell_xml.add_attribute("x", round_dec(ell.x, precision));
+        ell_xml.add_attribute("y", round_dec(ell.y, precision));
+        ell_xml.add_attribute("width", round_dec(ell.width, precision));
+        ell_xml.add_attribute("height", round_dec(ell.height, precision));
         ell_xml.add_attribute("antialias", ell.antialias);
         ell_xml.add_attribute("style", &ell.style);
         ell_xml
@@ -169,11 +182,132 @@ impl Bounding for Ellipse {
     }
 }
 
-impl ScaleEntity for Ellipse {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x *= fact_x;
-        self.y *= fact_y;
-        self.width *= fact_x;
-        self.height *= fact_y;
+impl Ellipse {
+    pub(super) fn is_circle(&self) -> bool {
+        (self.width - self.height).abs() < 1e-9
+    }
+}
+
+//Only for matrices that keep the ellipse's shape, anything else needs to be a polygon instead
+impl Transform for Ellipse {
+    fn transform(&mut self, matrix: &Matrix) {
+        let (rx, ry) = (self.width / 2.0, self.height / 2.0);
+        let (cx, cy) = matrix.apply(self.x + rx, self.y + ry);
+        let (half_width, half_height) = matrix.radii(rx, ry);
+
+        self.x = cx - half_width;
+        self.y = cy - half_height;
+        self.width = half_width * 2.0;
+        self.height = half_height * 2.0;
+    }
+}
+
+impl From<&Ellipse> for Polygon {
+    fn from(ell: &Ellipse) -> Self {
+        let (rx, ry) = (ell.width / 2.0, ell.height / 2.0);
+        let mut coordinates: Vec<Coordinate> = sweep(0.0, 0.0, true)
+            .map(|angle| {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                Coordinate {
+                    x: ell.x + rx + rx * cos,
+                    y: ell.y + ry - ry * sin,
+                }
+            })
+            .collect();
+
+        //closed, so the last point doesn't need to repeat the first
+        coordinates.pop();
+        Polygon::with_style(coordinates, true, &ell.style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dxf::{Point, Vector};
+
+    fn ellipse(major: (f64, f64), start: f64, end: f64) -> entities::Ellipse {
+        entities::Ellipse {
+            center: Point::new(10.0, 5.0, 0.0),
+            major_axis: Vector::new(major.0, major.1, 0.0),
+            minor_axis_ratio: 0.5,
+            start_parameter: start,
+            end_parameter: end,
+            ..Default::default()
+        }
+    }
+
+    fn assert_coordinate(actual: &Coordinate, x: f64, y: f64) {
+        assert!(
+            (actual.x - x).abs() < 1e-9 && (actual.y - y).abs() < 1e-9,
+            "({}, {}) != ({x}, {y})",
+            actual.x,
+            actual.y
+        );
+    }
+
+    #[test]
+    fn axis_angles() {
+        assert_eq!(axis_angle(&ellipse((4.0, 0.0), 0.0, 0.0)), Some(0.0));
+        assert_eq!(axis_angle(&ellipse((0.0, 4.0), 0.0, 0.0)), Some(90.0));
+        assert_eq!(axis_angle(&ellipse((-4.0, 0.0), 0.0, 0.0)), Some(180.0));
+        assert_eq!(axis_angle(&ellipse((0.0, -4.0), 0.0, 0.0)), Some(270.0));
+        assert_eq!(axis_angle(&ellipse((3.0, 4.0), 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn full_turns() {
+        assert!(is_full(&ellipse((4.0, 0.0), 0.0, 2.0 * PI)));
+        assert!(is_full(&ellipse((4.0, 0.0), 1.0, 1.0 + 2.0 * PI)));
+        assert!(is_full(&ellipse((4.0, 0.0), 0.0, 0.0)));
+        assert!(!is_full(&ellipse((4.0, 0.0), 0.0, PI)));
+    }
+
+    #[test]
+    fn only_full_axis_aligned_ellipses_are_qet_ellipses() {
+        //standing up, so the major axis is the height
+        let ell = Ellipse::try_from(&ellipse((0.0, 4.0), 0.0, 2.0 * PI)).unwrap();
+        assert_eq!((ell.width, ell.height), (4.0, 8.0));
+        assert_eq!((ell.x, ell.y), (8.0, -9.0));
+
+        assert_eq!(
+            Ellipse::try_from(&ellipse((3.0, 4.0), 0.0, 2.0 * PI)).unwrap_err(),
+            ErrorKind::Rotated
+        );
+        assert_eq!(
+            Ellipse::try_from(&ellipse((4.0, 0.0), 0.0, PI)).unwrap_err(),
+            ErrorKind::PartialEllipse
+        );
+    }
+
+    #[test]
+    fn partial_ellipse_points() {
+        let poly = Polygon::from(&ellipse((4.0, 0.0), 0.0, PI / 2.0));
+        //both ends are included, every 5 degrees of the parameter
+        assert_eq!(poly.coordinates.len(), 19);
+        assert_coordinate(&poly.coordinates[0], 14.0, -5.0);
+        assert_coordinate(&poly.coordinates[18], 10.0, -7.0);
+
+        //the arc can run through the start of the parameter
+        let poly = Polygon::from(&ellipse((4.0, 0.0), 1.5 * PI, 0.5 * PI));
+        assert_eq!(poly.coordinates.len(), 37);
+        assert_coordinate(&poly.coordinates[18], 14.0, -5.0);
+    }
+
+    #[test]
+    fn full_turn_ellipse_points() {
+        //starting part way around, the end parameter is a whole turn further along
+        let poly = Polygon::from(&ellipse((4.0, 0.0), PI / 2.0, PI / 2.0 + 2.0 * PI));
+        //closed, so the start isn't repeated at the end
+        assert_eq!(poly.coordinates.len(), 72);
+        assert_coordinate(&poly.coordinates[0], 10.0, -7.0);
+        assert_coordinate(&poly.coordinates[36], 10.0, -3.0);
+        assert_coordinate(&poly.coordinates[54], 14.0, -5.0);
+
+        //rotated, the minor axis is turned along with the major one
+        let poly = Polygon::from(&ellipse((3.0, 4.0), 0.0, 2.0 * PI));
+        assert_eq!(poly.coordinates.len(), 72);
+        assert_coordinate(&poly.coordinates[0], 13.0, -9.0);
+        assert_coordinate(&poly.coordinates[18], 8.0, -6.5);
     }
 }
@@ -0,0 +1,165 @@
+use dxf::entities::Entity;
+use dxf::Handle;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt::Display;
+
+use super::stats::entity_type_name;
+
+/// Why a single entity couldn't be converted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The entity type isn't supported yet.
+    Unsupported,
+
+    /// The entity has no (or not enough) points to make anything out of.
+    Empty,
+
+    /// An insert refers to a block that isn't in the drawing.
+    BlockNotFound(String),
+
+    /// A block that inserts itself, directly or through other blocks.
+    RecursiveBlock(String),
+
+    /// A polyline can only be turned into a line if it has exactly 2 points.
+    NotALine { vertices: usize },
+
+    /// The polyline isn't close enough to a circle to turn into an ellipse.
+    NotCircular,
+
+    /// The polyline isn't close enough to a rectangle to turn into a rect.
+    NotRectangular,
+
+    /// The entity is on a layer that the layer filters leave out.
+    Filtered,
+
+    /// The attribute is flagged as invisible.
+    Invisible,
+
+    /// The attribute definition is filled in by an attribute of the insert, which is used instead.
+    FilledIn,
+
+    /// QET ellipses and arcs can't be rotated.
+    Rotated,
+
+    /// Only a part of the ellipse is drawn, so it can't be a QET ellipse.
+    PartialEllipse,
+
+    /// The group codes of a hatch boundary didn't make sense, hatches are read from the .dxf
+    /// file separately so they don't get checked by the dxf crate like everything else.
+    Unreadable,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "unsupported entity type"),
+            Self::Empty => write!(f, "entity is empty"),
+            Self::BlockNotFound(name) => write!(f, "block {name} not found"),
+            Self::RecursiveBlock(name) => write!(f, "block {name} inserts itself"),
+            Self::NotALine { vertices } => {
+                write!(
+                    f,
+                    "can't convert a polyline with {vertices} points into a line"
+                )
+            }
+            Self::NotCircular => write!(f, "polyline has poor circularity, can't convert"),
+            Self::NotRectangular => {
+                write!(
+                    f,
+                    "polyline does not appear to be rectangular, can't convert"
+                )
+            }
+            Self::Filtered => write!(f, "layer is filtered out"),
+            Self::Invisible => write!(f, "attribute is invisible"),
+            Self::FilledIn => write!(f, "attribute definition is filled in by the insert"),
+            Self::Rotated => write!(f, "ellipse is rotated, can't convert"),
+            Self::PartialEllipse => write!(f, "ellipse is only partially drawn, can't convert"),
+            Self::Unreadable => write!(f, "couldn't read the boundary of the hatch"),
+        }
+    }
+}
+
+/// An entity that couldn't be converted, along with enough information to find it in the drawing.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ConversionError {
+    /// The dxf entity type name, e.g. "LINE".
+    pub entity_type: &'static str,
+
+    /// The handle of the entity in the drawing.
+    pub handle: Handle,
+
+    /// The layer the entity is on.
+    pub layer: String,
+
+    /// Names of the blocks the entity is nested in, outermost first. Empty for entities
+    /// that aren't in a block.
+    pub block_path: Vec<String>,
+
+    /// Why it couldn't be converted.
+    pub kind: ErrorKind,
+}
+
+impl ConversionError {
+    /// The entity couldn't be converted for the given reason.
+    pub fn new(ent: &Entity, block_path: &[String], kind: ErrorKind) -> Self {
+        ConversionError {
+            entity_type: entity_type_name(&ent.specific),
+            handle: ent.common.handle,
+            layer: ent.common.layer.clone(),
+            block_path: block_path.to_vec(),
+            kind,
+        }
+    }
+}
+
+//how an entity is pointed out in the messages, e.g. LINE (handle 2F, layer 0, block A/B)
+pub(crate) fn entity_location(
+    entity_type: &str,
+    handle: &str,
+    layer: &str,
+    block_path: &[String],
+) -> String {
+    let mut location = format!("{entity_type} (handle {handle}, layer {layer}");
+    if !block_path.is_empty() {
+        location.push_str(", block ");
+        location.push_str(&block_path.join("/"));
+    }
+    location.push(')');
+    location
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            entity_location(
+                self.entity_type,
+                &self.handle.as_string(),
+                &self.layer,
+                &self.block_path
+            ),
+            self.kind
+        )
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+//Handle doesn't implement Serialize (without the dxf serialize feature), and the
+//hex string is how the handle shows up in the dxf anyway
+impl Serialize for ConversionError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ConversionError", 6)?;
+        state.serialize_field("entity_type", self.entity_type)?;
+        state.serialize_field("handle", &self.handle.as_string())?;
+        state.serialize_field("layer", &self.layer)?;
+        state.serialize_field("block_path", &self.block_path)?;
+        state.serialize_field("kind", &self.kind)?;
+        state.serialize_field("reason", &self.kind.to_string())?;
+        state.end()
+    }
+}
@@ -0,0 +1,800 @@
+use dxf::{entities, Handle, Vector};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::{info, trace};
+
+use super::bulge::{sweep, BulgeArc};
+use super::ocs::Ocs;
+use super::polygon::{Coordinate, Point};
+use super::{ConversionOptions, Line, Objects, Polygon};
+
+//The dxf crate doesn't read HATCH entities at all, it just skips over them while loading.
+//So they are read separately here, straight from the group codes of the .dxf file, and
+//kept by the block they are in so they can be added in next to the rest of the block.
+//Only ascii .dxf files are read, binary files just end up without any hatches
+#[derive(Debug, Default)]
+pub struct Hatches {
+    entities: Vec<Hatch>,
+    blocks: HashMap<String, Vec<Hatch>>,
+    //there are hatches in the binary .dxf file that couldn't be read
+    binary: bool,
+}
+
+impl Hatches {
+    pub fn parse(dxf: &[u8]) -> Self {
+        let mut hatches = Hatches::default();
+        if dxf.starts_with(b"AutoCAD Binary DXF") {
+            //entity types are null terminated strings in binary files, so there is no need to
+            //go through the file to know whether any hatches are being left out
+            hatches.binary = dxf.windows(6).any(|window| window == b"HATCH\0");
+            if hatches.binary {
+                info!("Hatches can't be read from binary .dxf files, they will be left out");
+            }
+            return hatches;
+        }
+
+        let text = String::from_utf8_lossy(dxf);
+        let mut lines = text.lines();
+        let mut pairs = Vec::new();
+        while let (Some(code), Some(value)) = (lines.next(), lines.next()) {
+            let Ok(code) = code.trim().parse::<i32>() else {
+                //not a .dxf file, loading the drawing will fail on it anyway
+                return hatches;
+            };
+            pairs.push((code, value.trim()));
+        }
+
+        //spline edges only have fit data from AutoCAD 2010 (AC1024) on. Before that the 97 after
+        //a spline edge is the count of source boundary objects that ends the whole path
+        let fit_data = pairs
+            .windows(2)
+            .find(|pairs| pairs[0] == (9, "$ACADVER"))
+            .is_some_and(|pairs| pairs[1].1 >= "AC1024");
+
+        let mut section = "";
+        let mut block: Option<&str> = None;
+        for (i, pair) in pairs.iter().enumerate() {
+            if pair.0 != 0 {
+                continue;
+            }
+
+            //everything up to the next 0 group code belongs to this entity, so every pair
+            //is only looked at twice, once here and once while walking through the file
+            let body = pairs[i + 1..]
+                .iter()
+                .position(|(code, _)| *code == 0)
+                .map_or(&pairs[i + 1..], |end| &pairs[i + 1..=i + end]);
+            let find = |code| body.iter().find(|pair| pair.0 == code).map(|pair| pair.1);
+
+            match *pair {
+                (0, "SECTION") => section = find(2).unwrap_or_default(),
+                (0, "BLOCK") => block = find(2),
+                (0, "ENDBLK") => block = None,
+                (0, "HATCH") if section == "ENTITIES" || section == "BLOCKS" => {
+                    let hatch = Hatch::parse(body, fit_data);
+                    match block {
+                        Some(block) => hatches
+                            .blocks
+                            .entry(block.to_string())
+                            .or_default()
+                            .push(hatch),
+                        None => hatches.entities.push(hatch),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        hatches
+    }
+
+    //the hatches that aren't in any block
+    pub(crate) fn in_entities(&self) -> &[Hatch] {
+        &self.entities
+    }
+
+    pub(crate) fn in_block(&self, name: &str) -> &[Hatch] {
+        self.blocks.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn left_out_of_binary(&self) -> bool {
+        self.binary
+    }
+}
+
+#[derive(Debug)]
+enum Edge {
+    Line(Point, Point),
+
+    //the angles are the actual angles in degrees, going from start to end in the direction
+    //given by ccw. In the .dxf file the angles of clockwise edges are mirrored
+    Arc {
+        center: Point,
+        radius: f64,
+        start: f64,
+        end: f64,
+        ccw: bool,
+    },
+
+    //same as the arc, except the angles are the ellipse parameters
+    Ellipse {
+        center: Point,
+        major: Point,
+        ratio: f64,
+        start: f64,
+        end: f64,
+        ccw: bool,
+    },
+    Spline {
+        degree: usize,
+        knots: Vec<f64>,
+        control_points: Vec<Point>,
+        fit_points: Vec<Point>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    //islands alternate between filled and not filled
+    Normal,
+
+    //only the outermost area is filled
+    Outer,
+
+    //everything inside the outermost boundary is filled
+    Ignore,
+}
+
+//one of the families of parallel lines a hatch pattern is made of, already scaled and rotated
+#[derive(Debug)]
+struct PatternLine {
+    angle: f64,
+    base: Point,
+    offset: Point,
+}
+
+#[derive(Debug)]
+pub(crate) struct Hatch {
+    pub handle: Handle,
+    pub layer: String,
+
+    //a hatch with a boundary that couldn't be read is kept anyway, so it can be reported
+    //along with the entities next to it
+    pub readable: bool,
+    color: i16,
+    solid: bool,
+    style: Style,
+    paths: Vec<Vec<Edge>>,
+    pattern_lines: Vec<PatternLine>,
+}
+
+//Walks through the group codes of a single hatch. The meaning of a group code depends on
+//where it shows up, e.g. 10/20 is a vertex, an edge point, or a seed point, so they have to be
+//read in the order they come in
+struct Reader<'a> {
+    pairs: &'a [(i32, &'a str)],
+    pos: usize,
+
+    //whether spline edges end with their fit data
+    fit_data: bool,
+}
+
+impl Reader<'_> {
+    //reads the next value, but only if it has the expected group code
+    fn value<T: FromStr>(&mut self, code: i32) -> Option<T> {
+        let (next_code, value) = self.pairs.get(self.pos)?;
+        if *next_code != code {
+            return None;
+        }
+        self.pos += 1;
+        value.parse().ok()
+    }
+
+    //x and y always have group codes 10 apart
+    fn point(&mut self, code: i32) -> Option<Point> {
+        Some(Point::new(self.value(code)?, self.value(code + 10)?))
+    }
+
+    //skips ahead to the next pair with one of the group codes
+    fn seek(&mut self, codes: &[i32]) -> Option<i32> {
+        while let Some((code, _)) = self.pairs.get(self.pos) {
+            if codes.contains(code) {
+                return Some(*code);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn count(&mut self, code: i32) -> Option<usize> {
+        self.value::<i64>(code)
+            .map(|count| usize::try_from(count).unwrap_or_default())
+    }
+
+    fn edge(&mut self) -> Option<Edge> {
+        match self.value::<i32>(72)? {
+            1 => Some(Edge::Line(self.point(10)?, self.point(11)?)),
+            2 => {
+                let center = self.point(10)?;
+                let radius = self.value(40)?;
+                let (start, end): (f64, f64) = (self.value(50)?, self.value(51)?);
+                let ccw = self.value::<i32>(73).unwrap_or(1) != 0;
+                let (start, end) = if ccw { (start, end) } else { (-start, -end) };
+                Some(Edge::Arc {
+                    center,
+                    radius,
+                    start,
+                    end,
+                    ccw,
+                })
+            }
+            3 => {
+                let center = self.point(10)?;
+                let major = self.point(11)?;
+                let ratio = self.value(40)?;
+                let (start, end): (f64, f64) = (self.value(50)?, self.value(51)?);
+                let ccw = self.value::<i32>(73).unwrap_or(1) != 0;
+                let (start, end) = if ccw { (start, end) } else { (-start, -end) };
+                Some(Edge::Ellipse {
+                    center,
+                    major,
+                    ratio,
+                    start,
+                    end,
+                    ccw,
+                })
+            }
+            4 => {
+                let degree = self.count(94)?;
+                let _rational = self.value::<i32>(73);
+                let _periodic = self.value::<i32>(74);
+                let knot_count = self.count(95)?;
+                let control_count = self.count(96)?;
+                let knots = (0..knot_count)
+                    .map(|_| self.value(40))
+                    .collect::<Option<Vec<f64>>>()?;
+
+                //the weights of rational splines are ignored, close enough for a symbol
+                let control_points = (0..control_count)
+                    .map(|_| {
+                        let pt = self.point(10);
+                        let _weight = self.value::<f64>(42);
+                        pt
+                    })
+                    .collect::<Option<Vec<Point>>>()?;
+                let fit_points = if self.fit_data {
+                    let fit_points = (0..self.count(97)?)
+                        .map(|_| self.point(11))
+                        .collect::<Option<Vec<Point>>>()?;
+
+                    //the tangents at the ends are only there with fit points, and not needed
+                    let _start_tangent = self.point(12);
+                    let _end_tangent = self.point(13);
+                    fit_points
+                } else {
+                    Vec::new()
+                };
+                Some(Edge::Spline {
+                    degree,
+                    knots,
+                    control_points,
+                    fit_points,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn path(&mut self) -> Option<Vec<Edge>> {
+        let flags = self.value::<i32>(92)?;
+
+        //polyline paths are a list of vertices with bulges instead of a list of edges
+        if flags & 2 != 0 {
+            let has_bulge = self.value::<i32>(72).unwrap_or(0) != 0;
+            let _closed = self.value::<i32>(73);
+            let vertices = (0..self.count(93)?)
+                .map(|_| {
+                    let pt = self.point(10)?;
+                    let bulge = if has_bulge {
+                        self.value(42).unwrap_or(0.0)
+                    } else {
+                        0.0
+                    };
+                    Some((pt, bulge))
+                })
+                .collect::<Option<Vec<(Point, f64)>>>()?;
+
+            //hatch boundaries are always closed, whatever the flag says
+            return Some(
+                vertices
+                    .iter()
+                    .zip(vertices.iter().cycle().skip(1))
+                    .map(|(&(p1, bulge), &(p2, _))| bulge_edge(p1, p2, bulge))
+                    .collect(),
+            );
+        }
+
+        (0..self.count(93)?).map(|_| self.edge()).collect()
+    }
+
+    fn pattern_line(&mut self) -> Option<PatternLine> {
+        let angle = self.value(53)?;
+        let base = Point::new(self.value(43)?, self.value(44)?);
+        let offset = Point::new(self.value(45)?, self.value(46)?);
+
+        //the dashes are left out, the lines are drawn solid
+        for _ in 0..self.count(79).unwrap_or_default() {
+            self.value::<f64>(49)?;
+        }
+
+        Some(PatternLine {
+            angle,
+            base,
+            offset,
+        })
+    }
+}
+
+impl Hatch {
+    fn parse(pairs: &[(i32, &str)], fit_data: bool) -> Self {
+        let mut reader = Reader {
+            pairs,
+            pos: 0,
+            fit_data,
+        };
+        let mut hatch = Hatch {
+            handle: Handle::empty(),
+            layer: "0".into(),
+            readable: false,
+            color: 256,
+            solid: false,
+            style: Style::Normal,
+            paths: Vec::new(),
+            pattern_lines: Vec::new(),
+        };
+
+        //the boundaries are in the OCS of the hatch, at the elevation of the hatch
+        let mut elevation = 0.0;
+        let mut extrusion = Vector::z_axis();
+
+        //the common entity codes come before the boundary paths
+        while let Some((code, value)) = pairs.get(reader.pos) {
+            match code {
+                5 => hatch.handle = Handle(u64::from_str_radix(value, 16).unwrap_or_default()),
+                8 => hatch.layer = (*value).to_string(),
+                62 => hatch.color = value.parse().unwrap_or(256),
+                70 => hatch.solid = *value == "1",
+                30 => elevation = value.parse().unwrap_or_default(),
+                210 => extrusion.x = value.parse().unwrap_or_default(),
+                220 => extrusion.y = value.parse().unwrap_or_default(),
+                230 => extrusion.z = value.parse().unwrap_or(1.0),
+                91 => break,
+                _ => {}
+            }
+            reader.pos += 1;
+        }
+
+        hatch.readable = hatch.read_paths(&mut reader).is_some();
+        if !hatch.readable {
+            hatch.paths.clear();
+            return hatch;
+        }
+
+        if reader.seek(&[75]).is_some() {
+            hatch.style = match reader.value::<i32>(75) {
+                Some(1) => Style::Outer,
+                Some(2) => Style::Ignore,
+                _ => Style::Normal,
+            };
+        }
+
+        if !hatch.solid && reader.seek(&[78]).is_some() {
+            for _ in 0..reader.count(78).unwrap_or_default() {
+                let Some(line) = reader.pattern_line() else {
+                    break;
+                };
+                hatch.pattern_lines.push(line);
+            }
+        }
+
+        if let Some(ocs) = Ocs::new(&extrusion) {
+            hatch.move_to_wcs(&ocs, elevation);
+        }
+
+        hatch
+    }
+
+    //Moves the boundaries and the pattern out of the OCS, so drawing the hatch doesn't need
+    //to know about it. Like for the entities, it's the view from above that ends up in QET
+    fn move_to_wcs(&mut self, ocs: &Ocs, elevation: f64) {
+        let point = |pt: &mut Point| {
+            let wcs = ocs.point(&dxf::Point::new(pt.x, pt.y, elevation));
+            *pt = Point::new(wcs.x, wcs.y);
+        };
+        let vector = |pt: &mut Point| {
+            let wcs = ocs.point(&dxf::Point::new(pt.x, pt.y, 0.0));
+            *pt = Point::new(wcs.x, wcs.y);
+        };
+
+        for edge in self.paths.iter_mut().flatten() {
+            match edge {
+                Edge::Line(p1, p2) => {
+                    point(p1);
+                    point(p2);
+                }
+                //a mirrored arc runs the other way around, between the mirrored angles
+                Edge::Arc {
+                    center,
+                    start,
+                    end,
+                    ccw,
+                    ..
+                } => {
+                    point(center);
+                    (*start, *end) = (ocs.angle(*start), ocs.angle(*end));
+                    if ocs.mirrored() {
+                        *ccw = !*ccw;
+                    }
+                }
+                //the minor axis is a quarter turn counter clockwise from the major axis,
+                //which is clockwise once mirrored, so the parameters run backwards
+                Edge::Ellipse {
+                    center,
+                    major,
+                    start,
+                    end,
+                    ccw,
+                    ..
+                } => {
+                    point(center);
+                    vector(major);
+                    if ocs.mirrored() {
+                        (*start, *end, *ccw) = (-*start, -*end, !*ccw);
+                    }
+                }
+                Edge::Spline {
+                    control_points,
+                    fit_points,
+                    ..
+                } => control_points
+                    .iter_mut()
+                    .chain(fit_points.iter_mut())
+                    .for_each(point),
+            }
+        }
+
+        for line in &mut self.pattern_lines {
+            line.angle = ocs.angle(line.angle);
+            point(&mut line.base);
+            vector(&mut line.offset);
+        }
+    }
+
+    fn read_paths(&mut self, reader: &mut Reader) -> Option<()> {
+        for _ in 0..reader.count(91)? {
+            reader.seek(&[92])?;
+            self.paths.push(reader.path()?);
+        }
+        Some(())
+    }
+}
+
+fn bulge_edge(p1: Point, p2: Point, bulge: f64) -> Edge {
+    let to_dxf = |pt: Point| dxf::Point::new(pt.x, pt.y, 0.0);
+    match BulgeArc::new(&to_dxf(p1), &to_dxf(p2), bulge) {
+        Some(arc) => Edge::Arc {
+            center: Point::new(arc.center.x, arc.center.y),
+            radius: arc.radius,
+            start: arc.start,
+            end: arc.end,
+            ccw: arc.ccw,
+        },
+        None => Edge::Line(p1, p2),
+    }
+}
+
+//pattern hatches over a large area with a small spacing can end up as a huge number of lines,
+//past this many the hatch is filled with the closest QET hatch filling instead
+const MAX_PATTERN_LINES: u32 = 2000;
+
+impl Edge {
+    fn points(&self, spline_step: u32) -> Vec<Point> {
+        match self {
+            Edge::Line(p1, p2) => vec![*p1, *p2],
+            Edge::Arc {
+                center,
+                radius,
+                start,
+                end,
+                ccw,
+            } => sweep(*start, *end, *ccw)
+                .map(|angle| {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    Point::new(center.x + radius * cos, center.y + radius * sin)
+                })
+                .collect(),
+            Edge::Ellipse {
+                center,
+                major,
+                ratio,
+                start,
+                end,
+                ccw,
+            } => sweep(*start, *end, *ccw)
+                .map(|param| {
+                    let (sin, cos) = param.to_radians().sin_cos();
+                    Point::new(
+                        center.x + major.x * cos - major.y * ratio * sin,
+                        center.y + major.y * cos + major.x * ratio * sin,
+                    )
+                })
+                .collect(),
+            Edge::Spline {
+                degree,
+                knots,
+                control_points,
+                fit_points,
+            } => {
+                //without a valid set of knots fall back to the fit points, or the control points
+                if control_points.is_empty() || knots.len() != control_points.len() + degree + 1 {
+                    return if fit_points.is_empty() {
+                        control_points.clone()
+                    } else {
+                        fit_points.clone()
+                    };
+                }
+
+                let spline = bspline::BSpline::new(*degree, control_points.clone(), knots.clone());
+                let (first, last) = spline.knot_domain();
+                let step = (last - first) / f64::from(spline_step.max(1));
+                (0..spline_step)
+                    .map(|i| spline.point(first + step * f64::from(i)))
+                    .chain(control_points.last().copied())
+                    .collect()
+            }
+        }
+    }
+}
+
+//even-odd test of whether the point is inside the closed path
+fn contains(path: &[Point], pt: Point) -> bool {
+    let mut inside = false;
+    for (p1, p2) in path.iter().zip(path.iter().cycle().skip(1)) {
+        if (p1.y > pt.y) != (p2.y > pt.y)
+            && pt.x < p1.x + (pt.y - p1.y) / (p2.y - p1.y) * (p2.x - p1.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+//twice the signed area of the closed path, positive when it goes counterclockwise
+fn signed_area(path: &[Point]) -> f64 {
+    path.iter()
+        .zip(path.iter().cycle().skip(1))
+        .map(|(p1, p2)| p1.x * p2.y - p2.x * p1.y)
+        .sum()
+}
+
+//QET polygons can't have holes, so the island is cut out by going over from the closest point
+//of the ring, around the island the other way, and back the same way. The two ways over cancel
+//out, so the island stays empty whichever fill rule the polygon is drawn with
+fn cut_out(ring: &mut Vec<Point>, island: &[Point]) {
+    let mut island = island.to_vec();
+    if (signed_area(ring) > 0.0) == (signed_area(&island) > 0.0) {
+        island.reverse();
+    }
+    let distance = |p1: Point, p2: Point| (p1.x - p2.x).powi(2) + (p1.y - p2.y).powi(2);
+    let (i, j) = (0..ring.len())
+        .flat_map(|i| (0..island.len()).map(move |j| (i, j)))
+        .min_by(|&(i1, j1), &(i2, j2)| {
+            distance(ring[i1], island[j1]).total_cmp(&distance(ring[i2], island[j2]))
+        })
+        .unwrap_or_default();
+
+    let mut spliced = ring[..=i].to_vec();
+    spliced.extend(island[j..].iter().chain(&island[..=j]));
+    spliced.extend(&ring[i..]);
+    *ring = spliced;
+}
+
+//the QET colors that line up with the first few AutoCAD color indexes, anything
+//else (including BYLAYER/BYBLOCK) is black like the rest of the element
+fn color_name(aci: i16) -> &'static str {
+    match aci {
+        1 => "red",
+        2 => "yellow",
+        3 => "green",
+        4 => "cyan",
+        5 => "blue",
+        6 => "magenta",
+        8 => "gray",
+        9 => "lightgray",
+        _ => "black",
+    }
+}
+
+impl Hatch {
+    pub(crate) fn draw(&self, options: &ConversionOptions) -> Vec<Objects> {
+        let paths: Vec<Vec<Point>> = self
+            .paths
+            .iter()
+            .map(|path| {
+                let mut points: Vec<Point> = Vec::new();
+                for pt in path
+                    .iter()
+                    .flat_map(|edge| edge.points(options.spline_step))
+                {
+                    //the end of one edge is the start of the next
+                    if points.last().map_or(true, |last| {
+                        (last.x - pt.x).abs() > 1e-9 || (last.y - pt.y).abs() > 1e-9
+                    }) {
+                        points.push(pt);
+                    }
+                }
+                //the polygons are closed anyway, so the last point doesn't need to repeat the first
+                if points.len() > 1 && {
+                    let (first, last) = (points[0], points[points.len() - 1]);
+                    (first.x - last.x).abs() < 1e-9 && (first.y - last.y).abs() < 1e-9
+                } {
+                    points.pop();
+                }
+                points
+            })
+            .filter(|points| points.len() >= 3)
+            .collect();
+
+        //QET polygons can't have holes, so work out how deep each path is nested in the others
+        let mut nested: Vec<(usize, &Vec<Point>)> = paths
+            .iter()
+            .map(|path| {
+                let depth = paths
+                    .iter()
+                    .filter(|other| !std::ptr::eq(*other, path) && contains(other, path[0]))
+                    .count();
+                (depth, path)
+            })
+            .collect();
+        nested.sort_by_key(|(depth, _)| *depth);
+        nested.retain(|(depth, _)| match self.style {
+            Style::Normal => true,
+            Style::Outer => *depth <= 1,
+            Style::Ignore => *depth == 0,
+        });
+
+        if !self.solid && options.hatch_patterns {
+            if let Some(lines) = self.pattern(&nested) {
+                return lines;
+            }
+            info!("Too many lines in a pattern hatch, filling it instead");
+        }
+
+        let color = color_name(self.color);
+        let filling = if self.solid {
+            color
+        } else {
+            self.pattern_filling()
+        };
+        trace!(
+            "Creating {} polygons for a hatch, filling {filling}",
+            nested.len()
+        );
+
+        //each filled area gets the islands right inside of it cut out, so nothing underneath is
+        //painted over. The deeper paths are filled areas of their own again
+        nested
+            .iter()
+            .filter(|(depth, _)| depth % 2 == 0)
+            .map(|(depth, outline)| {
+                let mut ring = (*outline).clone();
+                for (_, island) in nested
+                    .iter()
+                    .filter(|(other, island)| *other == depth + 1 && contains(outline, island[0]))
+                {
+                    cut_out(&mut ring, island);
+                }
+                Objects::Polygon(Polygon::filled(
+                    ring.iter()
+                        .map(|pt| Coordinate { x: pt.x, y: -pt.y })
+                        .collect(),
+                    filling,
+                    color,
+                ))
+            })
+            .collect()
+    }
+
+    //QET only has horizontal, vertical and diagonal hatch fillings, so go with whichever
+    //is closest to the first line of the pattern
+    #[allow(clippy::cast_possible_truncation)]
+    fn pattern_filling(&self) -> &'static str {
+        let angle = self
+            .pattern_lines
+            .first()
+            .map_or(45.0, |line| line.angle)
+            .rem_euclid(180.0);
+        match ((angle / 45.0).round() as i64) % 4 {
+            0 => "hor",
+            1 => "bdiag",
+            2 => "ver",
+            _ => "fdiag",
+        }
+    }
+
+    //draws the pattern as lines clipped to the boundary paths, going in and out of the
+    //hatched area every time a line crosses a path takes care of the islands
+    fn pattern(&self, nested: &[(usize, &Vec<Point>)]) -> Option<Vec<Objects>> {
+        let edges: Vec<(Point, Point)> = nested
+            .iter()
+            .flat_map(|(_, path)| {
+                path.iter()
+                    .copied()
+                    .zip(path.iter().copied().cycle().skip(1))
+            })
+            .collect();
+
+        let mut lines = Vec::new();
+        for pattern in &self.pattern_lines {
+            let (sin, cos) = pattern.angle.to_radians().sin_cos();
+
+            //the lines are spaced out by the part of the offset that is perpendicular to them
+            let spacing = pattern.offset.y * cos - pattern.offset.x * sin;
+            if spacing.abs() < 1e-9 {
+                continue;
+            }
+            let across = |pt: Point| {
+                (pt.y * cos - pt.x * sin - (pattern.base.y * cos - pattern.base.x * sin)) / spacing
+            };
+            let (low, high) = edges
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(low, high), (pt, _)| {
+                    (low.min(across(*pt)), high.max(across(*pt)))
+                });
+            if high - low > f64::from(MAX_PATTERN_LINES) {
+                return None;
+            }
+
+            //there are only so many lines, so the numbers of the first and last one fit easily
+            #[allow(clippy::cast_possible_truncation)]
+            let (first, last) = (low.ceil() as i32, high.floor() as i32);
+            for i in first..=last {
+                let i = f64::from(i);
+                let mut crossings: Vec<f64> = edges
+                    .iter()
+                    .filter_map(|(p1, p2)| {
+                        let (d1, d2) = (across(*p1) - i, across(*p2) - i);
+                        if (d1 > 0.0) == (d2 > 0.0) {
+                            return None;
+                        }
+                        let t = d1 / (d1 - d2);
+                        let (x, y) = (p1.x + t * (p2.x - p1.x), p1.y + t * (p2.y - p1.y));
+                        Some(x * cos + y * sin)
+                    })
+                    .collect();
+                crossings.sort_by(f64::total_cmp);
+
+                //any point on the line, the crossings are measured along the line from there
+                let along = pattern.base.x * cos + pattern.base.y * sin;
+                let origin = Point::new(
+                    pattern.base.x - sin * spacing * i - cos * along,
+                    pattern.base.y + cos * spacing * i - sin * along,
+                );
+                for pair in crossings.chunks_exact(2) {
+                    let at = |dist: f64| {
+                        dxf::Point::new(origin.x + cos * dist, origin.y + sin * dist, 0.0)
+                    };
+                    lines.push(Objects::Line(Line::from(&entities::Line::new(
+                        at(pair[0]),
+                        at(pair[1]),
+                    ))));
+                }
+                if lines.len() > MAX_PATTERN_LINES as usize {
+                    return None;
+                }
+            }
+        }
+
+        Some(lines)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::qelmt::Bounding;
 
-use super::two_dec;
+use super::transform::{Matrix, Transform};
+use super::ErrorKind;
 use super::LineEnd;
-use super::ScaleEntity;
+use super::{round_dec, ToXml};
 use dxf::entities::{self, LwPolyline, Polyline};
 use simple_xml_builder::XMLElement;
 
@@ -50,12 +51,35 @@ impl From<&entities::Line> for Line {
     }
 }
 
+impl Line {
+    //puts arrows on the ends of the line, length is the size of the arrows
+    pub(super) fn arrows(self, end1: bool, end2: bool, length: f64) -> Self {
+        let arrow = |on| {
+            if on {
+                LineEnd::SimpleArrow
+            } else {
+                LineEnd::None
+            }
+        };
+
+        Line {
+            end1: arrow(end1),
+            length1: length,
+            end2: arrow(end2),
+            length2: length,
+            ..self
+        }
+    }
+}
+
 impl TryFrom<&Polyline> for Line {
-    type Error = &'static str; //add better error later
+    type Error = ErrorKind;
 
     fn try_from(poly: &Polyline) -> Result<Self, Self::Error> {
         if poly.__vertices_and_handles.len() != 2 {
-            return Err("Error can't convert polyline with more than 2 points into a Line");
+            return Err(ErrorKind::NotALine {
+                vertices: poly.__vertices_and_handles.len(),
+            });
         }
 
         Ok(Line {
@@ -82,11 +106,13 @@ impl TryFrom<&Polyline> for Line {
 }
 
 impl TryFrom<&LwPolyline> for Line {
-    type Error = &'static str; //add better error later
+    type Error = ErrorKind;
 
     fn try_from(poly: &LwPolyline) -> Result<Self, Self::Error> {
         if poly.vertices.len() != 2 {
-            return Err("Error can't convert polyline with more than 2 points into a Line");
+            return Err(ErrorKind::NotALine {
+                vertices: poly.vertices.len(),
+            });
         }
 
         Ok(Line {
@@ -156,16 +182,17 @@ impl From<&entities::Leader> for Leader {
     }
 }
 
-impl From<&Line> for XMLElement {
-    fn from(line: &Line) -> Self {
+impl ToXml for Line {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let line = self;
         let mut line_xml = XMLElement::new("line");
-        line_xml.add_attribute("x1", two_dec(line.x1));
-        line_xml.add_attribute("y1", two_dec(line.y1));
-        line_xml.add_attribute("length1", two_dec(line.length1));
+        line_xml.add_attribute("x1", round_dec(line.x1, precision));
+        line_xml.add_attribute("y1", round_dec(line.y1, precision));
+        line_xml.add_attribute("length1", round_dec(line.length1, precision));
         line_xml.add_attribute("end1", &line.end1);
-        line_xml.add_attribute("x2", two_dec(line.x2));
-        line_xml.add_attribute("y2", two_dec(line.y2));
-        line_xml.add_attribute("length2", two_dec(line.length2));
+        line_xml.add_attribute("x2", round_dec(line.x2, precision));
+        line_xml.add_attribute("y2", round_dec(line.y2, precision));
+        line_xml.add_attribute("length2", round_dec(line.length2, precision));
         line_xml.add_attribute("end2", &line.end2);
         line_xml.add_attribute("antialias", line.antialias);
         line_xml.add_attribute("style", &line.style);
@@ -191,13 +218,10 @@ impl Bounding for Line {
     }
 }
 
-impl ScaleEntity for Line {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x1 *= fact_x;
-        self.x2 *= fact_x;
-
-        self.y1 *= fact_y;
-        self.y2 *= fact_y;
+impl Transform for Line {
+    fn transform(&mut self, matrix: &Matrix) {
+        (self.x1, self.y1) = matrix.apply(self.x1, self.y1);
+        (self.x2, self.y2) = matrix.apply(self.x2, self.y2);
 
         //while writing this scaling code, I'm looking at
         //QET_ElementScaler from plc-user to see if there are
@@ -206,10 +230,12 @@ impl ScaleEntity for Line {
         //to 99.0, but I'm not sure why at the moment. I'll go
         //ahead and limit them as well, and try to come back to
         //figure out what the purpose here is
-        self.length1 *= fact_x.min(fact_y);
+        //mirroring or rotating doesn't change the size of the ends though
+        let fact = matrix.scale_x().min(matrix.scale_y());
+        self.length1 *= fact;
         self.length1 = self.length1.min(99.0);
 
-        self.length2 *= fact_x.min(fact_y);
+        self.length2 *= fact;
         self.length2 = self.length2.min(99.0);
     }
 }
@@ -1,18 +1,20 @@
-use dxf::entities::{AttributeDefinition, Entity, EntityType};
-use dxf::entities::{LwPolyline, Polyline};
+use dxf::entities::{Attribute, AttributeDefinition, Entity, EntityCommon, EntityType, Insert};
 use dxf::enums::{AttachmentPoint, HorizontalTextJustification, Units, VerticalTextJustification};
+use dxf::tables::DimStyle;
 use dxf::{Block, Drawing};
 use dynamictext::DTextBuilder;
 use hex_color::HexColor;
 use itertools::Itertools;
 use simple_xml_builder::XMLElement;
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::f64::consts::PI;
 use std::fmt::Display;
+use std::str::FromStr;
 use uuid::Uuid;
 
-use tracing::{debug, error, info, span, trace, Level};
+use tracing::{error, info, span, trace, Level};
 
 pub mod arc;
 pub use arc::Arc;
@@ -35,21 +37,51 @@ pub use ellipse::Ellipse;
 pub mod rectangle;
 pub use rectangle::Rectangle;
 
-fn find_block<'a>(drw: &'a Drawing, name: &str) -> Option<&'a Block> {
-    //this is ugly there has to be a cleaner way to filter this....but for my first attempt at pulling the
-    //blocks out of the drawing it works.
-    //I mean would this ever return more than 1? I would assume block names have to be unique?
-    //but maybe not, the blocks have a handle, which is a u64. There is a get by handle function
-    //but not a get by name function....maybe the handle is what is unique and there can be duplicate names?
-    //a quick glance through the dxf code it looks like the handle might be given to the library user when inserting
-    //an entity? So I don't think there is any easy way to get the handle
-    drw.blocks().filter(|bl| bl.name == name).take(1).next()
+pub mod terminal;
+pub use terminal::Terminal;
+
+pub mod stats;
+pub use stats::ConversionStats;
+
+pub mod error;
+pub use error::{ConversionError, ErrorKind};
+
+pub mod options;
+pub use options::ConversionOptions;
+
+mod mtext;
+
+mod dimension;
+use dimension::Dimension;
+
+mod bulge;
+use bulge::Bulges;
+
+mod ocs;
+
+mod transform;
+use transform::{Matrix, Transform};
+
+pub mod hatch;
+use hatch::Hatch;
+pub use hatch::Hatches;
+
+//Controls how TEXT and MTEXT entities end up in the element. QET supports both a
+//static <text> element, and a <dynamic_text> element
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextMode {
+    #[default]
+    Static,
+    Dynamic,
 }
 
-#[derive(Debug)]
-enum Either<L, R> {
-    Left(L),
-    Right(R),
+//Controls what the arcs of bulged polyline segments end up as. Either extra points along
+//the arc in the polygon, or the polyline gets split up into QET lines and arcs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BulgeMode {
+    #[default]
+    Points,
+    Arcs,
 }
 
 #[derive(Debug)]
@@ -63,10 +95,11 @@ pub struct Definition {
     link_type: LinkType,
     uuid: ElmtUuid,
     names: Names,
+    kind_infos: Option<KindInfos>,
     element_infos: Option<ElemInfos>,
     informations: &'static str,
     description: Description,
-    //counts
+    stats: ConversionStats,
 }
 
 trait Bounding {
@@ -81,24 +114,23 @@ trait Bounding {
     }
 }
 
-trait ScaleEntity: Bounding {
-    fn scale(&mut self, fact_x: f64, fact_y: f64);
+//From<&T> for XMLElement can't take the number of decimals to write out, and a
+//tuple can't be used for it either since neither the tuple nor XMLElement are ours
+trait ToXml {
+    fn to_xml(&self, precision: u8) -> XMLElement;
 }
 
 trait Arity {
-    fn match_range() -> std::ops::RangeInclusive<f64> {
-        //this boundary of 2% has been chosen arbitrarily, I might adjust this later
-        //I know in one of my sample files, I'm getting a value of 0.99....
-        //since Associated Constants in a trait can't have a default value
-        //I'm using this function that defaults to a constant range of 2%
-        //Then I could also easily overwrite it if I wanted to change the tolerance
+    fn match_range(tolerance: f64) -> std::ops::RangeInclusive<f64> {
+        //the tolerance comes from the conversion options (2% by default)
+        //I could still easily overwrite this if I wanted to change the range
         //for a specific type
-        0.98..=1.02
+        (1.0 - tolerance)..=(1.0 + tolerance)
     }
 
     // Uses the Shoelace Formula to calculate the area of a polygon from an
     // iterator of coordinates
-    fn area<'a>(coords: impl Iterator<Item = &'a dxf::Point> + Clone + ExactSizeIterator) -> f64 {
+    fn area<'a>(coords: impl ExactSizeIterator<Item = &'a dxf::Point> + Clone) -> f64 {
         let len = coords.len();
         (coords
             .circular_tuple_windows()
@@ -111,210 +143,116 @@ trait Arity {
 }
 
 trait Circularity: Arity {
-    fn is_circular(&self) -> bool;
+    fn is_circular(&self, tolerance: f64) -> bool;
 }
 
 trait Rectangularity: Arity {
-    fn is_rectangular(&self) -> bool;
+    fn is_rectangular(&self, tolerance: f64) -> bool;
 }
 
-impl Bounding for Polyline {
+//The bounds take the arcs of any bulged segments into account, not just the vertices
+impl<T: Bulges> Bounding for T {
     fn left_bound(&self) -> f64 {
-        if let Some(vtx) = self.vertices().min_by(|v1, v2| {
-            v1.location
-                .x
-                .partial_cmp(&v2.location.x)
-                .unwrap_or(Ordering::Greater)
-        }) {
-            vtx.location.x
-        } else {
-            0.0
-        }
-    }
-
-    fn right_bound(&self) -> f64 {
-        if let Some(vtx) = self.vertices().max_by(|v1, v2| {
-            v1.location
-                .x
-                .partial_cmp(&v2.location.x)
-                .unwrap_or(Ordering::Less)
-        }) {
-            vtx.location.x
-        } else {
-            0.0
-        }
-    }
-
-    fn top_bound(&self) -> f64 {
-        if let Some(vtx) = self.vertices().max_by(|v1, v2| {
-            v1.location
-                .y
-                .partial_cmp(&v2.location.y)
-                .unwrap_or(Ordering::Less)
-        }) {
-            vtx.location.y
-        } else {
-            0.0
-        }
-    }
-
-    fn bot_bound(&self) -> f64 {
-        if let Some(vtx) = self.vertices().min_by(|v1, v2| {
-            v1.location
-                .y
-                .partial_cmp(&v2.location.y)
-                .unwrap_or(Ordering::Less)
-        }) {
-            vtx.location.y
-        } else {
-            0.0
-        }
-    }
-}
-
-impl Arity for Polyline {}
-
-impl Circularity for Polyline {
-    fn is_circular(&self) -> bool {
-        let perim: f64 = {
-            let tmp_pts: Vec<dxf::Point> = self.vertices().map(|v| v.clone().location).collect();
-            let len = tmp_pts.len();
-            tmp_pts
-                .into_iter()
-                .circular_tuple_windows()
-                .map(|(fst, sec)| ((fst.x - sec.x).powf(2.0) + (fst.y - sec.y).powf(2.0)).sqrt())
-                .take(len)
-                .sum()
-        };
-
-        let vertices: Vec<_> = self.vertices().map(|v| &v.location).collect();
-        let area = Polyline::area(vertices.into_iter());
-        let t_ratio = 4.0 * PI * area / perim.powf(2.0);
-
-        Self::match_range().contains(&t_ratio)
-    }
-}
-
-impl Rectangularity for Polyline {
-    fn is_rectangular(&self) -> bool {
-        let vertices: Vec<_> = self.vertices().map(|v| &v.location).collect();
-        let bounding_area = self.bounding_area();
-
-        let area = Polyline::area(vertices.into_iter());
-        Self::match_range().contains(&(area / bounding_area))
-    }
-}
-
-impl Bounding for LwPolyline {
-    fn left_bound(&self) -> f64 {
-        if let Some(vtx) = self
-            .vertices
+        self.outline()
             .iter()
-            .min_by(|v1, v2| v1.x.partial_cmp(&v2.x).unwrap_or(Ordering::Greater))
-        {
-            vtx.x
-        } else {
-            0.0
-        }
+            .map(|pt| pt.x)
+            .reduce(f64::min)
+            .unwrap_or(0.0)
     }
 
     fn right_bound(&self) -> f64 {
-        if let Some(vtx) = self
-            .vertices
+        self.outline()
             .iter()
-            .max_by(|v1, v2| v1.x.partial_cmp(&v2.x).unwrap_or(Ordering::Less))
-        {
-            vtx.x
-        } else {
-            0.0
-        }
+            .map(|pt| pt.x)
+            .reduce(f64::max)
+            .unwrap_or(0.0)
     }
 
     fn top_bound(&self) -> f64 {
-        if let Some(vtx) = self
-            .vertices
+        self.outline()
             .iter()
-            .max_by(|v1, v2| v1.y.partial_cmp(&v2.y).unwrap_or(Ordering::Less))
-        {
-            vtx.y
-        } else {
-            0.0
-        }
+            .map(|pt| pt.y)
+            .reduce(f64::max)
+            .unwrap_or(0.0)
     }
 
     fn bot_bound(&self) -> f64 {
-        if let Some(vtx) = self
-            .vertices
+        self.outline()
             .iter()
-            .min_by(|v1, v2| v1.y.partial_cmp(&v2.y).unwrap_or(Ordering::Less))
-        {
-            vtx.y
-        } else {
-            0.0
-        }
+            .map(|pt| pt.y)
+            .reduce(f64::min)
+            .unwrap_or(0.0)
     }
 }
 
-impl Arity for LwPolyline {}
+impl<T: Bulges> Arity for T {}
 
-impl Circularity for LwPolyline {
-    fn is_circular(&self) -> bool {
-        let perim: f64 = self
-            .vertices
+//Both checks are done on the outline, so the arcs of bulged segments count too. That way
+//a polyline made of two half circles is seen as the circle it is
+impl<T: Bulges> Circularity for T {
+    fn is_circular(&self, tolerance: f64) -> bool {
+        let outline = self.outline();
+        let perim: f64 = outline
             .iter()
             .circular_tuple_windows()
-            .map(|(fst, sec)| {
-                ((fst.x - sec.x).powf(2.0) + (fst.y - sec.y).powf(2.0))
-                    .abs()
-                    .sqrt()
-            })
-            .take(self.vertices.len())
+            .map(|(fst, sec)| ((fst.x - sec.x).powf(2.0) + (fst.y - sec.y).powf(2.0)).sqrt())
+            .take(outline.len())
             .sum();
 
-        let vertices: Vec<_> = self
-            .vertices
-            .iter()
-            .map(|v| dxf::Point::new(v.x, v.y, 0.0))
-            .collect();
-
-        let area = LwPolyline::area(vertices.iter());
+        let area = Self::area(outline.iter());
         let t_ratio = 4.0 * PI * area / perim.powf(2.0);
 
-        Self::match_range().contains(&t_ratio)
+        Self::match_range(tolerance).contains(&t_ratio)
     }
 }
 
-impl Rectangularity for LwPolyline {
-    fn is_rectangular(&self) -> bool {
+impl<T: Bulges> Rectangularity for T {
+    fn is_rectangular(&self, tolerance: f64) -> bool {
         let bounding_area = self.bounding_area();
-        let vertices: Vec<_> = self
-            .vertices
-            .iter()
-            .map(|v| dxf::Point::new(v.x, v.y, 0.0))
-            .collect();
-        let area = LwPolyline::area(vertices.iter());
+        let area = Self::area(self.outline().iter());
 
-        Self::match_range().contains(&(area / bounding_area))
+        Self::match_range(tolerance).contains(&(area / bounding_area))
     }
 }
 
 impl Definition {
-    pub fn new(name: impl Into<String>, spline_step: u32, drw: &Drawing) -> Self {
+    pub fn new(
+        name: impl Into<String>,
+        options: &ConversionOptions,
+        drw: &Drawing,
+        hatches: &Hatches,
+    ) -> Self {
         /*for st in drw.styles() {
             dbg!(st);
         }*/
-        let scale_factor = Self::scale_factor(drw.header.default_drawing_units);
+        let scale_factor = options
+            .scale
+            .unwrap_or_else(|| Self::scale_factor(drw.header.default_drawing_units));
+        let stats = RefCell::new(ConversionStats::default());
         let description = {
-            let mut description: Description = (drw, spline_step).into();
-            description.scale(scale_factor, scale_factor);
+            let mut description: Description = (drw, hatches, options, &stats).into();
+            description.transform(&Matrix::scale(scale_factor, scale_factor));
+            if let Some(namespace) = &options.uuid_namespace {
+                description.number_uuids(namespace);
+            }
             description
         };
+        let mut stats = stats.into_inner();
+        stats.count_objects(
+            description
+                .objects
+                .iter()
+                .flat_map(|obj| std::iter::once(obj).chain(obj.descendants())),
+        );
 
         //The below calculation for width and hotspot_x are taken from the qet source code
         let (width, hotspot_x) = {
             let tmp_width = description.right_bound() - description.left_bound();
+            //an element is nowhere near big enough for these casts to lose anything
+            #[allow(clippy::cast_possible_truncation)]
             let int_width = tmp_width.round() as i64;
             let upwidth = ((int_width / 10) * 10) + 10;
+            #[allow(clippy::cast_precision_loss)]
             let xmargin = (upwidth as f64 - tmp_width).round();
 
             let width = if int_width % 10 > 6 {
@@ -323,17 +261,19 @@ impl Definition {
                 upwidth
             };
 
-            (
-                width,
-                -((description.left_bound() - (xmargin / 2.0)).round() as i64),
-            )
+            #[allow(clippy::cast_possible_truncation)]
+            let hotspot_x = -((description.left_bound() - (xmargin / 2.0)).round() as i64);
+            (width, hotspot_x)
         };
 
         //The below calculation for height and hotspot_y are taken from the qet source code
         let (height, hotspot_y) = {
             let tmp_height = description.bot_bound() - description.top_bound();
+            //an element is nowhere near big enough for these casts to lose anything
+            #[allow(clippy::cast_possible_truncation)]
             let int_height = tmp_height.round() as i64;
             let upheight = ((int_height / 10) * 10) + 10;
+            #[allow(clippy::cast_precision_loss)]
             let ymargin = (upheight as f64 - tmp_height).round();
 
             let height = if int_height % 10 > 6 {
@@ -342,12 +282,16 @@ impl Definition {
                 upheight
             };
 
-            (
-                height,
-                -((description.top_bound() - (ymargin / 2.0)).round() as i64),
-            )
+            #[allow(clippy::cast_possible_truncation)]
+            let hotspot_y = -((description.top_bound() - (ymargin / 2.0)).round() as i64);
+            (height, hotspot_y)
         };
 
+        let mut names = Names::new("en", name); //need to truncate the extension
+        for (lang, name) in &options.names {
+            names.set(lang, name);
+        }
+
         Definition {
             r#type: ItemType::Element,
             width,
@@ -355,20 +299,49 @@ impl Definition {
             hotspot_x,
             hotspot_y,
             version: "0.8.0".into(),
-            link_type: LinkType::Simple,
-            uuid: Uuid::new_v4().into(),
-            names: Names {
-                names: vec![Name {
-                    lang: "en".into(),
-                    value: name.into(), //need to truncate the extension
-                }],
-            },
-            element_infos: None,
+            link_type: options.link_type,
+            uuid: options
+                .uuid_namespace
+                .map_or_else(Uuid::new_v4, |namespace| {
+                    Uuid::new_v5(&namespace, b"element")
+                })
+                .into(),
+            names,
+            kind_infos: KindInfos::new(options.link_type, &options.kind_infos),
+            element_infos: ElemInfos::from_description(&description),
             informations: "Created using dxf2elmt!",
             description,
+            stats,
         }
     }
 
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn hotspot(&self) -> (i64, i64) {
+        (self.hotspot_x, self.hotspot_y)
+    }
+
+    pub fn stats(&self) -> &ConversionStats {
+        &self.stats
+    }
+
+    pub fn to_xml(&self) -> XMLElement {
+        self.into()
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails
+    pub fn write_xml<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
+        self.to_xml().write(writer)
+    }
+
     fn scale_factor(unit: Units) -> f64 {
         //so per discussion at https://qelectrotech.org/forum/viewtopic.php?pid=20685#p20685
         //we are in agreement to scale things to 1mm = 2px;
@@ -409,6 +382,12 @@ impl Definition {
     }
 }
 
+impl Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_xml())
+    }
+}
+
 impl From<&Definition> for XMLElement {
     fn from(def: &Definition) -> Self {
         let mut def_xml = XMLElement::new("definition");
@@ -417,11 +396,14 @@ impl From<&Definition> for XMLElement {
         def_xml.add_attribute("hotspot_x", def.hotspot_x);
         def_xml.add_attribute("hotspot_y", def.hotspot_y);
         def_xml.add_attribute("version", &def.version);
-        def_xml.add_attribute("link_type", &def.link_type);
+        def_xml.add_attribute("link_type", def.link_type);
         def_xml.add_attribute("type", &def.r#type);
 
         def_xml.add_child((&def.uuid).into());
         def_xml.add_child((&def.names).into());
+        if let Some(kinds) = &def.kind_infos {
+            def_xml.add_child(kinds.into());
+        }
         if let Some(einfos) = &def.element_infos {
             def_xml.add_child(einfos.into());
         }
@@ -445,7 +427,7 @@ pub(crate) enum Objects {
     DynamicText(DynamicText),
     Text(Text),
     Line(Line),
-    //Terminal(Terminal),
+    Terminal(Terminal),
     Group(Vec<Objects>),
 }
 
@@ -462,6 +444,15 @@ impl Objects {
             _ => Children { slice: [].iter() },
         }
     }
+
+    //a group with nothing but (empty) groups in it, when everything in a block was filtered
+    //out. It has no bounds, so it's left out when working out the bounds of what it's in
+    pub(crate) fn is_empty(&self) -> bool {
+        matches!(self, Objects::Group(_))
+            && self
+                .descendants()
+                .all(|obj| matches!(obj, Objects::Group(_)))
+    }
 }
 
 pub(crate) struct Descendants<'a> {
@@ -511,8 +502,9 @@ impl Bounding for Objects {
             Objects::DynamicText(dynamic_text) => dynamic_text.left_bound(),
             Objects::Text(text) => text.left_bound(),
             Objects::Line(line) => line.left_bound(),
+            Objects::Terminal(terminal) => terminal.left_bound(),
             Objects::Group(vec) => {
-                let lb = vec.iter().min_by(|ob1, ob2| {
+                let lb = vec.iter().filter(|ob| !ob.is_empty()).min_by(|ob1, ob2| {
                     ob1.left_bound()
                         .partial_cmp(&ob2.left_bound())
                         .unwrap_or(Ordering::Greater)
@@ -536,8 +528,9 @@ impl Bounding for Objects {
             Objects::DynamicText(dynamic_text) => dynamic_text.right_bound(),
             Objects::Text(text) => text.right_bound(),
             Objects::Line(line) => line.right_bound(),
+            Objects::Terminal(terminal) => terminal.right_bound(),
             Objects::Group(vec) => {
-                let rb = vec.iter().max_by(|ob1, ob2| {
+                let rb = vec.iter().filter(|ob| !ob.is_empty()).max_by(|ob1, ob2| {
                     ob1.right_bound()
                         .partial_cmp(&ob2.right_bound())
                         .unwrap_or(Ordering::Less)
@@ -561,8 +554,9 @@ impl Bounding for Objects {
             Objects::DynamicText(dynamic_text) => dynamic_text.top_bound(),
             Objects::Text(text) => text.top_bound(),
             Objects::Line(line) => line.top_bound(),
+            Objects::Terminal(terminal) => terminal.top_bound(),
             Objects::Group(vec) => {
-                let tb = vec.iter().min_by(|ob1, ob2| {
+                let tb = vec.iter().filter(|ob| !ob.is_empty()).min_by(|ob1, ob2| {
                     ob1.top_bound()
                         .partial_cmp(&ob2.top_bound())
                         .unwrap_or(Ordering::Greater)
@@ -586,8 +580,9 @@ impl Bounding for Objects {
             Objects::DynamicText(dynamic_text) => dynamic_text.bot_bound(),
             Objects::Text(text) => text.bot_bound(),
             Objects::Line(line) => line.bot_bound(),
+            Objects::Terminal(terminal) => terminal.bot_bound(),
             Objects::Group(vec) => {
-                let bb = vec.iter().max_by(|ob1, ob2| {
+                let bb = vec.iter().filter(|ob| !ob.is_empty()).max_by(|ob1, ob2| {
                     ob1.bot_bound()
                         .partial_cmp(&ob2.bot_bound())
                         .unwrap_or(Ordering::Less)
@@ -603,56 +598,146 @@ impl Bounding for Objects {
     }
 }
 
-impl ScaleEntity for Objects {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        match self {
-            Objects::Arc(arc) => arc.scale(fact_x, fact_y),
-            Objects::Ellipse(ellipse) => ellipse.scale(fact_x, fact_y),
-            Objects::Polygon(polygon) => polygon.scale(fact_x, fact_y),
-            Objects::Rectangle(rectangle) => rectangle.scale(fact_x, fact_y),
-            Objects::DynamicText(dynamic_text) => dynamic_text.scale(fact_x, fact_y),
-            Objects::Text(text) => text.scale(fact_x, fact_y),
-            Objects::Line(line) => line.scale(fact_x, fact_y),
-            Objects::Group(vec) => vec.iter_mut().for_each(|ob| ob.scale(fact_x, fact_y)),
+//QET ellipses, arcs and rectangles can't be rotated or skewed, so when a matrix does
+//that to them they get broken up into a polygon first
+impl Transform for Objects {
+    fn transform(&mut self, matrix: &Matrix) {
+        let reshaped = match self {
+            Objects::Arc(arc) if !matrix.keeps_shape(arc.is_circle()) => {
+                Some(Objects::Polygon((&*arc).into()))
+            }
+            Objects::Ellipse(ellipse) if !matrix.keeps_shape(ellipse.is_circle()) => {
+                Some(Objects::Polygon((&*ellipse).into()))
+            }
+            Objects::Rectangle(rectangle) if !matrix.keeps_shape(false) => {
+                Some(Objects::Polygon((&*rectangle).into()))
+            }
+            _ => None,
+        };
+        if let Some(reshaped) = reshaped {
+            *self = reshaped;
         }
-    }
-}
 
-#[derive(Debug)]
-struct ScaleFactor {
-    x: f64,
-    y: f64,
-}
-
-impl Default for ScaleFactor {
-    fn default() -> Self {
-        ScaleFactor { x: 1.0, y: 1.0 }
+        match self {
+            Objects::Arc(arc) => arc.transform(matrix),
+            Objects::Ellipse(ellipse) => ellipse.transform(matrix),
+            Objects::Polygon(polygon) => polygon.transform(matrix),
+            Objects::Rectangle(rectangle) => rectangle.transform(matrix),
+            Objects::DynamicText(dynamic_text) => dynamic_text.transform(matrix),
+            Objects::Text(text) => text.transform(matrix),
+            Objects::Line(line) => line.transform(matrix),
+            Objects::Terminal(terminal) => terminal.transform(matrix),
+            Objects::Group(vec) => vec.iter_mut().for_each(|ob| ob.transform(matrix)),
+        }
     }
 }
 
-#[derive(Default, Debug)]
-pub(crate) struct Offset {
-    x: f64,
-    y: f64,
+//Hatches aren't entities as far as the dxf crate is concerned, so they don't go through the
+//ObjectsBuilder. But they are filtered, counted and placed the same way as the entities next to them
+fn build_hatches(
+    hatches: &[Hatch],
+    options: &ConversionOptions,
+    matrix: &Matrix,
+    insert_layer: Option<&str>,
+    stats: Option<&RefCell<ConversionStats>>,
+    block_path: &[String],
+) -> Vec<Objects> {
+    hatches
+        .iter()
+        .filter(|hatch| {
+            let layer = match insert_layer {
+                Some(insert_layer) if hatch.layer == "0" => insert_layer,
+                _ => &hatch.layer,
+            };
+            options.includes_layer(layer)
+        })
+        .flat_map(|hatch| {
+            if let Some(stats) = stats {
+                let mut stats = stats.borrow_mut();
+                stats.count_entity_type("HATCH");
+                if !hatch.readable {
+                    let err = ConversionError {
+                        entity_type: "HATCH",
+                        handle: hatch.handle,
+                        layer: hatch.layer.clone(),
+                        block_path: block_path.to_vec(),
+                        kind: ErrorKind::Unreadable,
+                    };
+                    stats.warnings.push(format!("{err}, left out"));
+                    stats.skip(err);
+                }
+            }
+            hatch.draw(options)
+        })
+        .map(|mut obj| {
+            obj.transform(matrix);
+            obj
+        })
+        .collect()
 }
 
 #[derive(Debug)]
-pub struct ObjectsBuilder<'a> {
+pub(crate) struct ObjectsBuilder<'a> {
     ent: &'a Entity,
-    spline_step: u32,
+    options: &'a ConversionOptions,
     blocks: &'a [&'a Block],
-    offset: Offset,
-    scale_fact: ScaleFactor,
+    dim_styles: &'a [&'a DimStyle],
+    hatches: Option<&'a Hatches>,
+
+    //places the entity, this is where all of the inserts it's nested in put it
+    matrix: Matrix,
+    stats: Option<&'a RefCell<ConversionStats>>,
+
+    //names of the blocks this entity is nested in, used for error reporting
+    block_path: &'a [String],
+
+    //layer of the insert this entity is nested in, entities on layer 0 in a block
+    //take on the layer of the insert, which matters for the layer filters
+    insert_layer: Option<&'a str>,
+
+    //tags of the attributes the insert this entity is directly in has filled in. The attribute
+    //definitions for those are left out, otherwise they'd show up along with the attributes
+    filled_tags: &'a [String],
 }
 
 impl<'a> ObjectsBuilder<'a> {
-    pub fn new(ent: &'a Entity, spline_step: u32) -> Self {
+    pub fn new(ent: &'a Entity, options: &'a ConversionOptions) -> Self {
         Self {
             ent,
-            spline_step,
+            options,
             blocks: &[],
-            offset: Offset::default(),
-            scale_fact: ScaleFactor::default(),
+            dim_styles: &[],
+            hatches: None,
+            matrix: Matrix::identity(),
+            stats: None,
+            block_path: &[],
+            insert_layer: None,
+            filled_tags: &[],
+        }
+    }
+
+    pub fn block_path(self, block_path: &'a [String]) -> Self {
+        Self { block_path, ..self }
+    }
+
+    pub fn insert_layer(self, insert_layer: &'a str) -> Self {
+        Self {
+            insert_layer: Some(insert_layer),
+            ..self
+        }
+    }
+
+    fn layer(&self) -> &'a str {
+        match self.insert_layer {
+            Some(insert_layer) if self.ent.common.layer == "0" => insert_layer,
+            _ => &self.ent.common.layer,
+        }
+    }
+
+    pub fn stats(self, stats: impl Into<Option<&'a RefCell<ConversionStats>>>) -> Self {
+        Self {
+            stats: stats.into(),
+            ..self
         }
     }
 
@@ -660,367 +745,438 @@ impl<'a> ObjectsBuilder<'a> {
         Self { blocks, ..self }
     }
 
-    pub fn offsets(self, x: f64, y: f64) -> Self {
+    pub fn dim_styles(self, dim_styles: &'a [&'a DimStyle]) -> Self {
+        Self { dim_styles, ..self }
+    }
+
+    pub fn hatches(self, hatches: &'a Hatches) -> Self {
         Self {
-            offset: Offset { x, y },
+            hatches: Some(hatches),
             ..self
         }
     }
 
-    pub fn scaling(self, fact_x: f64, fact_y: f64) -> Self {
+    pub fn matrix(self, matrix: Matrix) -> Self {
+        Self { matrix, ..self }
+    }
+
+    pub fn filled_tags(self, filled_tags: &'a [String]) -> Self {
         Self {
-            scale_fact: ScaleFactor {
-                x: fact_x,
-                y: fact_y,
-            },
+            filled_tags,
             ..self
         }
     }
 
-    #[allow(clippy::too_many_lines)]
-    pub fn build(self) -> Result<Objects, &'static str /*add better error later*/> {
-        match &self.ent.specific {
-            EntityType::Circle(circle) => {
-                let mut ellipse: Ellipse = circle.into();
+    //Entities that are left out on purpose, so they don't show up in the stats. That's the
+    //ones on layers that are filtered out, invisible attributes, and attribute definitions
+    //that have been filled in by an attribute of the insert
+    fn left_out(&self) -> Option<ErrorKind> {
+        if !self.options.includes_layer(self.layer()) {
+            return Some(ErrorKind::Filtered);
+        }
 
-                ellipse.scale(self.scale_fact.x, self.scale_fact.y);
-                ellipse.x += self.offset.x;
-                ellipse.y -= self.offset.y;
-                Ok(Objects::Ellipse(ellipse))
+        match &self.ent.specific {
+            EntityType::Attribute(attrib) if attrib.is_invisible() => Some(ErrorKind::Invisible),
+            EntityType::AttributeDefinition(attdef)
+                if !attdef.is_constant()
+                    && self
+                        .filled_tags
+                        .iter()
+                        .any(|tag| tag.eq_ignore_ascii_case(&attdef.text_tag)) =>
+            {
+                Some(ErrorKind::FilledIn)
             }
-            EntityType::Line(line) => {
-                let mut line: Line = line.into();
+            _ => None,
+        }
+    }
 
-                line.scale(self.scale_fact.x, self.scale_fact.y);
+    //the 24 bit color is kept in an i32, only the bits of it matter
+    #[allow(clippy::cast_sign_loss)]
+    fn color(&self) -> HexColor {
+        HexColor::from_u32(self.ent.common.color_24_bit as u32)
+    }
+
+    //for entities that were converted, but not quite the way they look in the drawing
+    fn warn(&self, warning: impl Display) {
+        if let Some(stats) = self.stats {
+            stats.borrow_mut().warn(self.ent, self.block_path, warning);
+        }
+    }
+
+    pub fn build(self) -> Result<Objects, ConversionError> {
+        let (ent, stats, block_path) = (self.ent, self.stats, self.block_path);
 
-                line.x1 += self.offset.x;
-                line.y1 -= self.offset.y;
+        if let Some(kind) = self.left_out() {
+            return Err(ConversionError::new(ent, block_path, kind));
+        }
 
-                line.x2 += self.offset.x;
-                line.y2 -= self.offset.y;
+        let res = self
+            .build_objects()
+            .map_err(|kind| ConversionError::new(ent, block_path, kind));
 
-                Ok(Objects::Line(line))
+        if let Some(stats) = stats {
+            let mut stats = stats.borrow_mut();
+            stats.count_entity(ent);
+            if let Err(e) = &res {
+                stats.skip(e.clone());
             }
-            EntityType::Arc(arc) => {
-                let mut arc: Arc = arc.into();
+        }
 
-                arc.scale(self.scale_fact.x, self.scale_fact.y);
+        res
+    }
 
-                arc.x += self.offset.x;
-                arc.y -= self.offset.y;
+    //builds all of the entities in a block into a group, this is used for inserts, and for
+    //the anonymous blocks that hold the graphics of a dimension
+    fn block_group(&self, block: &Block, matrix: Matrix, filled_tags: &[String]) -> Objects {
+        let block_path: Vec<String> = self
+            .block_path
+            .iter()
+            .cloned()
+            .chain(std::iter::once(block.name.clone()))
+            .collect();
 
-                Ok(Objects::Arc(arc))
-            }
+        let hatches = self
+            .hatches
+            .map_or(&[][..], |hatches| hatches.in_block(&block.name));
+
+        Objects::Group(
+            build_hatches(
+                hatches,
+                self.options,
+                &matrix,
+                Some(self.layer()),
+                self.stats,
+                &block_path,
+            )
+            .into_iter()
+            .chain(block.entities.iter().filter_map(|ent| {
+                let mut builder = ObjectsBuilder::new(ent, self.options)
+                    .matrix(matrix)
+                    .blocks(self.blocks)
+                    .dim_styles(self.dim_styles)
+                    .stats(self.stats)
+                    .block_path(&block_path)
+                    .insert_layer(self.layer())
+                    .filled_tags(filled_tags);
+                if let Some(hatches) = self.hatches {
+                    builder = builder.hatches(hatches);
+                }
+                builder.build().ok()
+            }))
+            .collect(),
+        )
+    }
+
+    //The attribute values filled in on an insert. They're already in drawing coordinates (not
+    //relative to the block), so they only need placing in whatever the insert is nested in
+    fn attributes(&self, ins: &Insert) -> Vec<Objects> {
+        ins.__attributes_and_handles
+            .iter()
+            .filter_map(|(attrib, handle)| {
+                //the dxf crate only keeps the handle of an attribute, so the rest of the
+                //common entity data (layer, color...) comes from the insert
+                let ent = Entity {
+                    common: EntityCommon {
+                        handle: *handle,
+                        ..self.ent.common.clone()
+                    },
+                    specific: EntityType::Attribute(attrib.clone()),
+                };
+
+                let mut builder = ObjectsBuilder::new(&ent, self.options)
+                    .matrix(self.matrix)
+                    .stats(self.stats)
+                    .block_path(self.block_path);
+                if let Some(insert_layer) = self.insert_layer {
+                    builder = builder.insert_layer(insert_layer);
+                }
+                builder.build().ok()
+            })
+            .collect()
+    }
+
+    //The entity converted into objects and put in place with the matrix. Inserts (and
+    //dimensions with a block) pass the matrix on to the entities of their block instead
+    #[allow(clippy::too_many_lines)]
+    fn build_objects(self) -> Result<Objects, ErrorKind> {
+        let wcs = ocs::to_wcs(&self.ent.specific);
+        let mut obj = match wcs.as_ref().unwrap_or(&self.ent.specific) {
+            EntityType::Circle(circle) => Objects::Ellipse(circle.into()),
+            EntityType::Line(line) => Objects::Line(line.into()),
+            EntityType::Arc(arc) => Objects::Arc(arc.into()),
             EntityType::Spline(spline) => {
-                let mut poly: Polygon = (spline, self.spline_step).into();
+                let poly: Polygon = (spline, self.options.spline_step).into();
 
                 match poly.coordinates.len() {
-                    0 | 1 => Err("Error removing empty Spline"),
+                    0 | 1 => return Err(ErrorKind::Empty),
                     //I'll need to improve my understanding of splines and the math here
                     //to make sure I do this correctly.
                     //2 => //convert to line
-                    _ => {
-                        poly.scale(self.scale_fact.x, self.scale_fact.y);
-                        for cord in &mut poly.coordinates {
-                            cord.x += self.offset.x;
-                            cord.y -= self.offset.y;
-                        }
-
-                        Ok(Objects::Polygon(poly))
-                    }
+                    _ => Objects::Polygon(poly),
                 }
             }
-            EntityType::Text(text) => {
-                Ok(
-                    //right now the dxf2elmt defaults to making all text Static Text...
-                    //it was requested by the QET devs to add in support for Dynamic text
-                    //which was added, but it defaults to OFF, and QET doesn't pass the parameter
-                    //to enable it...I'm wondering if it makes more sense to default to use dynamic text
-                    //for now I'll set it to use dynamic text, and once I get the CLI flag passing through
-                    //I might change the default parameter to use Dynamic Text
-                    if false {
-                        //how best to pass in the flag for dynamic text or not....should the flag also default to true?
-                        let mut text: Text = (
-                            text,
-                            HexColor::from_u32(self.ent.common.color_24_bit as u32),
-                        )
-                            .into();
-
-                        text.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        text.x += self.offset.x;
-                        text.y -= self.offset.y;
-
-                        Objects::Text(text)
-                    } else {
-                        let mut dtext = DTextBuilder::from_text(text)
-                            .color(HexColor::from_u32(self.ent.common.color_24_bit as u32))
-                            .build();
-
-                        dtext.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        dtext.x += self.offset.x;
-                        dtext.y -= self.offset.y;
-
-                        Objects::DynamicText(dtext)
-                    },
-                )
-            }
+            EntityType::Text(text) => match self.options.text_mode {
+                TextMode::Static => Objects::Text((text, self.color()).into()),
+                TextMode::Dynamic => {
+                    Objects::DynamicText(DTextBuilder::from_text(text).color(self.color()).build())
+                }
+            },
             EntityType::Ellipse(ellipse) => {
-                let mut ellipse: Ellipse = ellipse.into();
-
-                ellipse.scale(self.scale_fact.x, self.scale_fact.y);
-                ellipse.x += self.offset.x;
-                ellipse.y -= self.offset.y;
-
-                Ok(Objects::Ellipse(ellipse))
+                //QET ellipses and arcs can't be rotated, so those end up as a polygon
+                match Ellipse::try_from(ellipse) {
+                    Ok(ellipse) => Objects::Ellipse(ellipse),
+                    Err(kind) => {
+                        if let Ok(arc) = Arc::try_from(ellipse) {
+                            Objects::Arc(arc)
+                        } else {
+                            self.warn(match kind {
+                                ErrorKind::PartialEllipse => "partial ellipse drawn as a polygon",
+                                _ => "rotated ellipse drawn as a polygon",
+                            });
+                            Objects::Polygon(ellipse.into())
+                        }
+                    }
+                }
             }
             EntityType::MText(mtext) => {
-                Ok(
-                    //right now the dxf2elmt defaults to making all text Static Text...
-                    //it was requested by the QET devs to add in support for Dynamic text
-                    //which was added, but it defaults to OFF, and QET doesn't pass the parameter
-                    //to enable it...I'm wondering if it makes more sense to default to use dynamic text
-                    //for now I'll set it to use dynamic text, and once I get the CLI flag passing through
-                    //I might change the default parameter to use Dynamic Text
-                    if false {
-                        //how best to pass in the flag for dynamic text or not....should the flag also default to true?
-                        /*let mut text: Text =
-                            (mtext, HexColor::from_u32(ent.common.color_24_bit as u32)).into();
-                            text.x += offset_x;
-                            text.y -= offset_y;
-                        Objects::Text(text)*/
-                        todo!();
-                    } else {
-                        let mut dtext = DTextBuilder::from_mtext(mtext)
-                            .color(HexColor::from_u32(self.ent.common.color_24_bit as u32))
-                            .build();
-
-                        dtext.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        dtext.x += self.offset.x;
-                        dtext.y -= self.offset.y;
+                let color = self.color();
+                let (spans, ignored) = mtext::parse(mtext);
+                if !ignored.is_empty() {
+                    let codes: Vec<String> =
+                        ignored.iter().map(|code| format!("\\{code}")).collect();
+                    self.warn(format_args!(
+                        "formatting codes {} have nothing to map onto in QET, left out",
+                        codes.join(", ")
+                    ));
+                }
+                let pieces = mtext::layout(spans, mtext);
+                let split = pieces.len() > 1;
 
-                        Objects::DynamicText(dtext)
-                    },
-                )
+                let mut objects: Vec<Objects> = pieces
+                    .iter()
+                    .map(|piece| match self.options.text_mode {
+                        TextMode::Static => {
+                            let mut text: Text = (mtext, color).into();
+                            text.apply_mtext_piece(piece);
+                            Objects::Text(text)
+                        }
+                        TextMode::Dynamic => {
+                            let mut dtext = DTextBuilder::from_mtext(mtext).color(color).build();
+                            dtext.apply_mtext_piece(piece, split);
+                            Objects::DynamicText(dtext)
+                        }
+                    })
+                    .collect();
+
+                //mixed formatting ends up as a group of texts
+                match objects.pop() {
+                    Some(obj) if !split => obj,
+                    Some(obj) => {
+                        objects.push(obj);
+                        Objects::Group(objects)
+                    }
+                    None => return Err(ErrorKind::Empty),
+                }
             }
             EntityType::Polyline(polyline) => match polyline.__vertices_and_handles.len() {
-                0 | 1 => Err("Error empty Polyline"),
-                2 => {
-                    let mut line = Line::try_from(polyline)?;
-
-                    line.scale(self.scale_fact.x, self.scale_fact.x);
-
-                    line.x1 += self.offset.x;
-                    line.y1 -= self.offset.y;
-
-                    line.x2 += self.offset.x;
-                    line.y2 -= self.offset.y;
-
-                    Ok(Objects::Line(line))
-                }
+                0 | 1 => return Err(ErrorKind::Empty),
+                2 if !polyline.has_bulges() => Objects::Line(Line::try_from(polyline)?),
                 _ => {
-                    if let Ok(mut ellipse) = Ellipse::try_from(polyline) {
-                        ellipse.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        ellipse.x += self.offset.x;
-                        ellipse.y -= self.offset.y;
-
-                        Ok(Objects::Ellipse(ellipse))
-                    } else if let Ok(mut rectangle) = Rectangle::try_from(polyline) {
-                        rectangle.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        rectangle.x += self.offset.x;
-                        rectangle.y -= self.offset.y;
-
-                        Ok(Objects::Rectangle(rectangle))
+                    if let Ok(ellipse) = Ellipse::try_from((polyline, self.options.shape_tolerance))
+                    {
+                        Objects::Ellipse(ellipse)
+                    } else if let Ok(rectangle) =
+                        Rectangle::try_from((polyline, self.options.shape_tolerance))
+                    {
+                        Objects::Rectangle(rectangle)
+                    } else if self.options.bulge_mode == BulgeMode::Arcs && polyline.has_bulges() {
+                        Objects::Group(polyline.lines_and_arcs())
                     } else {
-                        let mut poly: Polygon = polyline.into();
-
-                        poly.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        for cord in &mut poly.coordinates {
-                            cord.x += self.offset.x;
-                            cord.y -= self.offset.y;
-                        }
-
-                        Ok(Objects::Polygon(poly))
+                        Objects::Polygon(polyline.into())
                     }
                 }
             },
             EntityType::LwPolyline(lwpolyline) => match lwpolyline.vertices.len() {
-                0 | 1 => Err("Error empty LwPolyline"),
-                2 => {
-                    let mut line = Line::try_from(lwpolyline)?;
-
-                    line.scale(self.scale_fact.x, self.scale_fact.y);
-
-                    line.x1 += self.offset.x;
-                    line.y1 -= self.offset.y;
-
-                    line.x2 += self.offset.x;
-                    line.y2 -= self.offset.y;
-
-                    Ok(Objects::Line(line))
-                }
+                0 | 1 => return Err(ErrorKind::Empty),
+                2 if !lwpolyline.has_bulges() => Objects::Line(Line::try_from(lwpolyline)?),
                 _ => {
-                    if let Ok(mut ellipse) = Ellipse::try_from(lwpolyline) {
-                        ellipse.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        ellipse.x += self.offset.x;
-                        ellipse.y -= self.offset.y;
-
-                        Ok(Objects::Ellipse(ellipse))
-                    } else if let Ok(mut rectangle) = Rectangle::try_from(lwpolyline) {
+                    if let Ok(ellipse) =
+                        Ellipse::try_from((lwpolyline, self.options.shape_tolerance))
+                    {
+                        Objects::Ellipse(ellipse)
+                    } else if let Ok(rectangle) =
+                        Rectangle::try_from((lwpolyline, self.options.shape_tolerance))
+                    {
                         // Hmm there still seem to be some issues here.
                         // I have a few examples with a polyline that is a perfect
                         // rectangle, but it's not a "closed" polyline, and has 5 points.
                         // it's getting converted as a 5 point polygon, not a rectangle.
                         // need to dig into why...is the extra point instead of being a closed
                         // polygon throwing off the area calculation? That would be my guess
-                        rectangle.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        rectangle.x += self.offset.x;
-                        rectangle.y -= self.offset.y;
-
-                        Ok(Objects::Rectangle(rectangle))
+                        Objects::Rectangle(rectangle)
+                    } else if self.options.bulge_mode == BulgeMode::Arcs && lwpolyline.has_bulges()
+                    {
+                        Objects::Group(lwpolyline.lines_and_arcs())
                     } else {
-                        let mut poly: Polygon = lwpolyline.into();
-
-                        poly.scale(self.scale_fact.x, self.scale_fact.y);
-
-                        for cord in &mut poly.coordinates {
-                            cord.x += self.offset.x;
-                            cord.y -= self.offset.y;
-                        }
-
-                        Ok(Objects::Polygon(poly))
+                        Objects::Polygon(lwpolyline.into())
                     }
                 }
             },
-            EntityType::Solid(solid) => {
-                let mut poly: Polygon = solid.into();
-
-                poly.scale(self.scale_fact.x, self.scale_fact.y);
-
-                for cord in &mut poly.coordinates {
-                    cord.x += self.offset.x;
-                    cord.y -= self.offset.y;
-                }
-
-                Ok(Objects::Polygon(poly))
-            }
+            EntityType::Solid(solid) => Objects::Polygon(solid.into()),
             EntityType::Insert(ins) => {
                 //info!("Found an Insert Block: {ins:?}");
                 info!("Found an Insert Block: {}", &ins.name);
                 let Some(block) = self.blocks.iter().find(|bl| bl.name == ins.name) else {
                     error!("Block {} not found", ins.name);
-                    return Err("Block Not Found");
+                    return Err(ErrorKind::BlockNotFound(ins.name.clone()));
                 };
+
+                //a block that ends up inserting itself would never stop nesting
+                if self.block_path.contains(&block.name) {
+                    error!("Block {} inserts itself", block.name);
+                    return Err(ErrorKind::RecursiveBlock(block.name.clone()));
+                }
                 trace!(
                     "Base Point: x: {} / y: {}",
                     block.base_point.x,
                     block.base_point.y
                 );
 
-                trace!("Creating Group from block {}. Pos(x:{}, y:{}). Offset(x:{}, y:{}). Scale(x:{}, y:{})",
-                    ins.name, ins.location.x, ins.location.y, self.offset.x, self.offset.y, self.scale_fact.x * ins.x_scale_factor,
-                    self.scale_fact.y * ins.y_scale_factor);
-                Ok(Objects::Group(
-                    block
-                        .entities
-                        .iter()
-                        .filter_map(|ent| {
-                            ObjectsBuilder::new(ent, self.spline_step)
-                                .offsets(
-                                    ins.location.x - block.base_point.x,
-                                    ins.location.y - block.base_point.y,
-                                )
-                                .scaling(
-                                    self.scale_fact.x * ins.x_scale_factor,
-                                    self.scale_fact.y * ins.y_scale_factor,
-                                )
-                                .blocks(self.blocks)
-                                .build()
-                                .ok()
-                        })
-                        .collect(),
-                ))
-            }
-            EntityType::Leader(leader) => {
-                let ld: Leader = leader.into();
-
-                Ok(Objects::Group(
-                    ld.0.into_iter()
-                        .map(|mut ln| {
-                            ln.scale(self.scale_fact.x, self.scale_fact.y);
+                trace!("Creating Group from block {}. Pos(x:{}, y:{}). Scale(x:{}, y:{}). Rotation: {}",
+                    ins.name, ins.location.x, ins.location.y, ins.x_scale_factor,
+                    ins.y_scale_factor, ins.rotation);
+
+                //the insert is placed inside of whatever it's nested in, so the matrices of
+                //all the inserts above it are applied on top, from the innermost out
+                let insert = Matrix::insert(ins, &block.base_point);
+
+                let filled_tags: Vec<String> = ins
+                    .attributes()
+                    .map(|attrib| attrib.attribute_tag.clone())
+                    .collect();
+
+                //a MINSERT is an array of the block, with a copy of it in every cell of the grid
+                let (columns, rows) = (ins.column_count.max(1), ins.row_count.max(1));
+
+                //a terminal block only marks where a terminal goes, it doesn't get drawn. The
+                //terminal is named after the first value filled in on the insert
+                if self.options.is_terminal_block(&block.name) {
+                    let name = ins
+                        .attributes()
+                        .map(|attrib| attrib.value.as_str())
+                        .find(|value| !value.is_empty())
+                        .unwrap_or_default();
+                    return Ok(Objects::Group(
+                        (0..rows)
+                            .cartesian_product(0..columns)
+                            .map(|(row, column)| {
+                                let cell = Matrix::array_cell(ins, column, row);
+                                let matrix = self.matrix * cell * insert;
+                                Objects::Terminal(Terminal::from_insert(
+                                    &matrix,
+                                    &block.base_point,
+                                    name,
+                                ))
+                            })
+                            .collect(),
+                    ));
+                }
 
-                            ln.x1 += self.offset.x;
-                            ln.y1 -= self.offset.y;
+                let group = if columns == 1 && rows == 1 {
+                    self.block_group(block, self.matrix * insert, &filled_tags)
+                } else {
+                    trace!(
+                        "Expanding array insert of {} into {columns}x{rows}",
+                        ins.name
+                    );
+                    Objects::Group(
+                        (0..rows)
+                            .cartesian_product(0..columns)
+                            .map(|(row, column)| {
+                                let cell = Matrix::array_cell(ins, column, row);
+                                self.block_group(block, self.matrix * cell * insert, &filled_tags)
+                            })
+                            .collect(),
+                    )
+                };
 
-                            ln.x2 += self.offset.x;
-                            ln.y2 -= self.offset.y;
+                if filled_tags.is_empty() {
+                    return Ok(group);
+                }
+                return Ok(Objects::Group(
+                    std::iter::once(group).chain(self.attributes(ins)).collect(),
+                ));
+            }
+            EntityType::RotatedDimension(_)
+            | EntityType::RadialDimension(_)
+            | EntityType::DiameterDimension(_)
+            | EntityType::AngularThreePointDimension(_)
+            | EntityType::OrdinateDimension(_) => {
+                let dim = Dimension::try_from(&self.ent.specific)?;
+                let base = dim.base();
+
+                //the dimension graphics are normally in an anonymous block (*D1, *D2...), which is
+                //already in drawing coordinates, so it goes exactly where the dimension itself would
+                if let Some(block) = self.blocks.iter().find(|bl| bl.name == base.block_name) {
+                    trace!("Creating Group from dimension block {}", base.block_name);
+                    return Ok(self.block_group(block, self.matrix, &[]));
+                }
 
-                            Objects::Line(ln)
-                        })
-                        .collect(),
-                ))
+                info!(
+                    "Dimension block {} not found, drawing the dimension instead",
+                    base.block_name
+                );
+                let style = self
+                    .dim_styles
+                    .iter()
+                    .find(|st| st.name.eq_ignore_ascii_case(&base.dimension_style_name))
+                    .into();
+                Objects::Group(dim.draw(&style, self.color()))
+            }
+            EntityType::Leader(leader) => {
+                let ld: Leader = leader.into();
+                Objects::Group(ld.0.into_iter().map(Objects::Line).collect())
             }
-            EntityType::AttributeDefinition(attrib) => Ok({
+            EntityType::AttributeDefinition(attrib) => {
                 //need to look up the proper way to get the color for the Attrib
-                let mut dtext = DTextBuilder::from_attrib(attrib)
-                    .color(HexColor::from_u32(self.ent.common.color_24_bit as u32))
-                    .build();
-
-                dtext.scale(self.scale_fact.x, self.scale_fact.y);
-
-                dtext.x += self.offset.x;
-                dtext.y -= self.offset.y;
-
-                Objects::DynamicText(dtext)
-            }),
+                Objects::DynamicText(
+                    DTextBuilder::from_attrib(attrib)
+                        .color(self.color())
+                        .info_name(self.options.info_name(&attrib.text_tag))
+                        .build(),
+                )
+            }
+            EntityType::ModelPoint(point) if self.options.is_terminal_layer(self.layer()) => {
+                Objects::Terminal(Terminal::new(point.location.x, point.location.y))
+            }
+            EntityType::Attribute(attrib) => Objects::DynamicText(
+                DTextBuilder::from_attribute(attrib)
+                    .color(self.color())
+                    .info_name(self.options.info_name(&attrib.attribute_tag))
+                    .build(),
+            ),
             _ => {
                 //dbg!(&self.ent.specific);
-                Err("Need to implement the rest of the entity types")
+                return Err(ErrorKind::Unsupported);
             }
-        }
-    }
-}
+        };
 
-impl From<&Objects> for Either<XMLElement, Vec<XMLElement>> {
-    fn from(obj: &Objects) -> Self {
-        match obj {
-            Objects::Arc(arc) => Either::Left(arc.into()),
-            Objects::Ellipse(ell) => Either::Left(ell.into()),
-            Objects::Polygon(poly) => Either::Left(poly.into()),
-            Objects::Rectangle(rect) => Either::Left(rect.into()),
-            Objects::DynamicText(dtext) => Either::Left(dtext.into()),
-            Objects::Text(txt) => Either::Left(txt.into()),
-            Objects::Line(line) => Either::Left(line.into()),
-            Objects::Group(block) => Either::Right(
-                block
-                    .iter()
-                    //.flatten()
-                    .filter_map(|obj| XMLElement::try_from(obj).ok())
-                    .collect(),
-            ),
-        }
+        obj.transform(&self.matrix);
+        Ok(obj)
     }
 }
 
-impl TryFrom<&Objects> for XMLElement {
-    type Error = &'static str; // add better error later
-
-    fn try_from(obj: &Objects) -> Result<Self, Self::Error> {
-        match obj {
-            Objects::Arc(arc) => Ok(arc.into()),
-            Objects::Ellipse(ell) => Ok(ell.into()),
-            Objects::Polygon(poly) => Ok(poly.into()),
-            Objects::Rectangle(rect) => Ok(rect.into()),
-            Objects::DynamicText(dtext) => Ok(dtext.into()),
-            Objects::Text(txt) => Ok(txt.into()),
-            Objects::Line(line) => Ok(line.into()),
+impl Objects {
+    fn try_to_xml(&self, precision: u8) -> Result<XMLElement, &'static str> {
+        match self {
+            Objects::Arc(arc) => Ok(arc.to_xml(precision)),
+            Objects::Ellipse(ell) => Ok(ell.to_xml(precision)),
+            Objects::Polygon(poly) => Ok(poly.to_xml(precision)),
+            Objects::Rectangle(rect) => Ok(rect.to_xml(precision)),
+            Objects::DynamicText(dtext) => Ok(dtext.to_xml(precision)),
+            Objects::Text(txt) => Ok(txt.to_xml(precision)),
+            Objects::Line(line) => Ok(line.to_xml(precision)),
+            Objects::Terminal(terminal) => Ok(terminal.to_xml(precision)),
             Objects::Group(_) => Err("Unsupported"),
         }
     }
@@ -1029,15 +1185,71 @@ impl TryFrom<&Objects> for XMLElement {
 #[derive(Debug)]
 pub struct Description {
     objects: Vec<Objects>,
+
+    //number of decimals to write out in the xml
+    precision: u8,
+}
+
+impl Description {
+    //every object that ends up in the element, in the order they're written out, with the
+    //groups they're in flattened out
+    fn leaves_mut(&mut self) -> Vec<&mut Objects> {
+        fn collect<'a>(objects: &'a mut [Objects], leaves: &mut Vec<&'a mut Objects>) {
+            for obj in objects {
+                if let Objects::Group(children) = obj {
+                    collect(children, leaves);
+                } else {
+                    leaves.push(obj);
+                }
+            }
+        }
+
+        let mut leaves = Vec::new();
+        collect(&mut self.objects, &mut leaves);
+        leaves
+    }
+
+    //the terminals marked with a POINT face whichever side of the element they're closest to
+    fn orient_terminals(&mut self) {
+        let bounds = (
+            self.left_bound(),
+            self.top_bound(),
+            self.right_bound(),
+            self.bot_bound(),
+        );
+
+        for obj in self.leaves_mut() {
+            if let Objects::Terminal(terminal) = obj {
+                terminal.orient_towards(bounds.0, bounds.1, bounds.2, bounds.3);
+            }
+        }
+    }
+
+    //Swaps the random uuids of the texts and terminals for ones worked out from the namespace
+    //and the position of the object in the element, so the same drawing always gets the same ones
+    fn number_uuids(&mut self, namespace: &Uuid) {
+        for (index, obj) in self.leaves_mut().into_iter().enumerate() {
+            let uuid = Uuid::new_v5(namespace, index.to_string().as_bytes());
+            match obj {
+                Objects::DynamicText(dynamic_text) => dynamic_text.set_uuid(uuid),
+                Objects::Terminal(terminal) => terminal.set_uuid(uuid),
+                _ => {}
+            }
+        }
+    }
 }
 
 impl Bounding for Description {
     fn left_bound(&self) -> f64 {
-        let lb = self.objects.iter().min_by(|ob1, ob2| {
-            ob1.left_bound()
-                .partial_cmp(&ob2.left_bound())
-                .unwrap_or(Ordering::Greater)
-        });
+        let lb = self
+            .objects
+            .iter()
+            .filter(|ob| !ob.is_empty())
+            .min_by(|ob1, ob2| {
+                ob1.left_bound()
+                    .partial_cmp(&ob2.left_bound())
+                    .unwrap_or(Ordering::Greater)
+            });
 
         if let Some(lb) = lb {
             lb.left_bound()
@@ -1047,25 +1259,33 @@ impl Bounding for Description {
     }
 
     fn right_bound(&self) -> f64 {
-        let rb = self.objects.iter().max_by(|ob1, ob2| {
-            ob1.right_bound()
-                .partial_cmp(&ob2.right_bound())
-                .unwrap_or(Ordering::Less)
-        });
+        let rb = self
+            .objects
+            .iter()
+            .filter(|ob| !ob.is_empty())
+            .max_by(|ob1, ob2| {
+                ob1.right_bound()
+                    .partial_cmp(&ob2.right_bound())
+                    .unwrap_or(Ordering::Less)
+            });
 
         if let Some(rb) = rb {
-            rb.left_bound()
+            rb.right_bound()
         } else {
             0.0
         }
     }
 
     fn top_bound(&self) -> f64 {
-        let tb = self.objects.iter().min_by(|ob1, ob2| {
-            ob1.top_bound()
-                .partial_cmp(&ob2.top_bound())
-                .unwrap_or(Ordering::Greater)
-        });
+        let tb = self
+            .objects
+            .iter()
+            .filter(|ob| !ob.is_empty())
+            .min_by(|ob1, ob2| {
+                ob1.top_bound()
+                    .partial_cmp(&ob2.top_bound())
+                    .unwrap_or(Ordering::Greater)
+            });
 
         if let Some(tb) = tb {
             tb.top_bound()
@@ -1075,25 +1295,27 @@ impl Bounding for Description {
     }
 
     fn bot_bound(&self) -> f64 {
-        let bb = self.objects.iter().max_by(|ob1, ob2| {
-            ob1.bot_bound()
-                .partial_cmp(&ob2.bot_bound())
-                .unwrap_or(Ordering::Less)
-        });
+        let bb = self
+            .objects
+            .iter()
+            .filter(|ob| !ob.is_empty())
+            .max_by(|ob1, ob2| {
+                ob1.bot_bound()
+                    .partial_cmp(&ob2.bot_bound())
+                    .unwrap_or(Ordering::Less)
+            });
 
         if let Some(bb) = bb {
-            bb.top_bound()
+            bb.bot_bound()
         } else {
             0.0
         }
     }
 }
 
-impl ScaleEntity for Description {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.objects
-            .iter_mut()
-            .for_each(|ob| ob.scale(fact_x, fact_y));
+impl Transform for Description {
+    fn transform(&mut self, matrix: &Matrix) {
+        self.objects.iter_mut().for_each(|ob| ob.transform(matrix));
     }
 }
 
@@ -1101,11 +1323,11 @@ impl From<&Description> for XMLElement {
     fn from(desc: &Description) -> Self {
         let mut desc_xml = XMLElement::new("description");
         for obj in &desc.objects {
-            if let Ok(elem) = XMLElement::try_from(obj) {
+            if let Ok(elem) = obj.try_to_xml(desc.precision) {
                 desc_xml.add_child(elem);
             }
             for obj in obj.descendants() {
-                if let Ok(elem) = XMLElement::try_from(obj) {
+                if let Ok(elem) = obj.try_to_xml(desc.precision) {
                     desc_xml.add_child(elem);
                 }
             }
@@ -1121,68 +1343,92 @@ impl From<&Description> for XMLElement {
         drw.entities().filter_map(|ent| Objects::try_from(ent).ok()).collect();
     }
 }*/
-impl From<(&Drawing, u32)> for Description {
-    fn from((drw, spline_step): (&Drawing, u32)) -> Self {
+impl
+    From<(
+        &Drawing,
+        &Hatches,
+        &ConversionOptions,
+        &RefCell<ConversionStats>,
+    )> for Description
+{
+    fn from(
+        (drw, hatches, options, stats): (
+            &Drawing,
+            &Hatches,
+            &ConversionOptions,
+            &RefCell<ConversionStats>,
+        ),
+    ) -> Self {
         let _from_drw_span = span!(Level::TRACE, "Converting Drawing to Description");
+        let blocks: Vec<&Block> = drw.blocks().collect();
+        let dim_styles: Vec<&DimStyle> = drw.dim_styles().collect();
+
+        if hatches.left_out_of_binary() {
+            stats
+                .borrow_mut()
+                .warnings
+                .push("hatches can't be read from binary .dxf files, they were left out".into());
+        }
 
-        Self {
-            objects: drw
-                .entities()
-                .filter_map(|ent| match &ent.specific {
-                    EntityType::Insert(ins) => {
-                        let block = find_block(drw, &ins.name)?;
-                        let blocks: Vec<&Block> = drw.blocks().collect();
-                        trace!(
-                            "Creating Group from block {}. Pos(x:{}, y:{}). Scale(x:{}, y:{})",
-                            ins.name,
-                            ins.location.x,
-                            ins.location.y,
-                            ins.x_scale_factor,
-                            ins.y_scale_factor
-                        );
-                        Some(Objects::Group(
-                            block
-                                .entities
-                                .iter()
-                                .filter_map(|ent| {
-                                    ObjectsBuilder::new(ent, spline_step)
-                                        //very confused here, in one test file if I leave out the ins locations here it puts things in the
-                                        //wrong location, and puts them in the correct location when I add the ins location in.
-                                        //but in another file it's the opposite, not sure why the difference...
-                                        .offsets(ins.location.x, ins.location.y)
-                                        .scaling(ins.x_scale_factor, ins.y_scale_factor)
-                                        .blocks(&blocks)
-                                        .build()
-                                        .ok()
-                                })
-                                .collect(),
-                        ))
-                    }
-                    _ => ObjectsBuilder::new(ent, spline_step).build().ok(),
-                })
+        //the hatches go first, so the fills end up underneath the rest of the drawing
+        let top_hatches = build_hatches(
+            hatches.in_entities(),
+            options,
+            &Matrix::identity(),
+            None,
+            Some(stats),
+            &[],
+        );
+
+        let mut description = Self {
+            objects: top_hatches
+                .into_iter()
+                //inserts go through the ObjectsBuilder like everything else, so top level inserts
+                //are placed exactly the same way as the ones nested in a block
+                .chain(drw.entities().filter_map(|ent| {
+                    ObjectsBuilder::new(ent, options)
+                        .blocks(&blocks)
+                        .dim_styles(&dim_styles)
+                        .hatches(hatches)
+                        .stats(stats)
+                        .build()
+                        .ok()
+                }))
                 .collect(),
-        }
+            precision: options.precision,
+        };
+        description.orient_terminals();
+        description
     }
 }
 
-//probably don't need to worry about this as they won't exist in the dxf...
-/*pub struct Terminal {
-    x: f64,
-    y: f64,
-    uuid: Uuid,
-    name: String,
-    orientation: TermOrient,
-    //type?
-    //  Generic
-    //  Indoor Terminal Block
-    //  External Terminal Block
-}*/
-
 #[derive(Debug)]
 pub struct Names {
     names: Vec<Name>,
 }
 
+impl Names {
+    pub fn new(lang: impl Into<String>, value: impl Into<String>) -> Self {
+        Names {
+            names: vec![Name {
+                lang: lang.into(),
+                value: value.into(),
+            }],
+        }
+    }
+
+    //sets the name for a language, replacing the existing name if that language already has one
+    pub fn set(&mut self, lang: impl Into<String>, value: impl Into<String>) {
+        let lang = lang.into();
+        let value = value.into();
+        if let Some(name) = self.names.iter_mut().find(|nm| nm.lang == lang) {
+            name.value = value;
+        } else {
+            self.names.push(Name { lang, value });
+        }
+    }
+}
+
 impl From<&Names> for XMLElement {
     fn from(nme: &Names) -> Self {
         let mut names_elmt = XMLElement::new("names");
@@ -1202,6 +1448,26 @@ pub struct Name {
     value: String,
 }
 
+//The qet_directory file QET uses to describe a category (folder) in an element collection
+#[derive(Debug)]
+pub struct ElementsCategory {
+    names: Names,
+}
+
+impl From<Names> for ElementsCategory {
+    fn from(names: Names) -> Self {
+        ElementsCategory { names }
+    }
+}
+
+impl From<&ElementsCategory> for XMLElement {
+    fn from(cat: &ElementsCategory) -> Self {
+        let mut cat_xml = XMLElement::new("qet-directory");
+        cat_xml.add_child((&cat.names).into());
+        cat_xml
+    }
+}
+
 #[derive(Debug)]
 pub struct ElmtUuid {
     uuid: Uuid,
@@ -1302,12 +1568,12 @@ impl From<HorizontalTextJustification> for HAlignment {
     fn from(value: HorizontalTextJustification) -> Self {
         //https://ezdxf.readthedocs.io/en/stable/tutorials/text.html#tut-text
         match value {
-            HorizontalTextJustification::Left => HAlignment::Left,
             HorizontalTextJustification::Center => HAlignment::Center,
             HorizontalTextJustification::Right => HAlignment::Right,
 
             //TODO: Handling the Aligned Middle and Fit alignments are a bit more complicated
             //for now I'll just default if it gets one of those we Alighn Left
+            //(this also covers HorizontalTextJustification::Left)
             _ => HAlignment::Left,
         }
     }
@@ -1388,15 +1654,41 @@ impl Display for LineEnd {
     }
 }
 
-#[derive(Debug)]
-enum LinkType {
+//What kind of element it is, which decides how QET links it to other elements. A master
+//(e.g. a coil) gets linked to its slaves (e.g. its contacts), reports link folios together
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    #[default]
     Simple,
-    /*Master,
+    Master,
     Slave,
     NextReport,
     PrevReport,
     TermBlock,
-    Thumbnail,*/
+    Thumbnail,
+}
+
+impl LinkType {
+    const ALL: [LinkType; 7] = [
+        Self::Simple,
+        Self::Master,
+        Self::Slave,
+        Self::NextReport,
+        Self::PrevReport,
+        Self::TermBlock,
+        Self::Thumbnail,
+    ];
+
+    //the kind informations QET expects for the link type, with the values the element
+    //editor starts a new element of that type off with
+    fn default_kind_infos(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Master => &[("type", "coil")],
+            Self::Slave => &[("type", "simple"), ("state", "NO"), ("number", "1")],
+            Self::TermBlock => &[("type", "generic"), ("function", "generic")],
+            _ => &[],
+        }
+    }
 }
 
 impl Display for LinkType {
@@ -1406,22 +1698,131 @@ impl Display for LinkType {
             "{}",
             match self {
                 Self::Simple => "simple",
-                /*Self::Master => "master",
+                Self::Master => "master",
                 Self::Slave => "slave",
                 Self::NextReport => "next_report",
                 Self::PrevReport => "previous_report",
                 Self::TermBlock => "terminal",
-                Self::Thumbnail => "thumbnail",*/
+                Self::Thumbnail => "thumbnail",
             }
         )
     }
 }
 
+//parses the name QET uses for the link type in the xml
+impl FromStr for LinkType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|link_type| link_type.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown link type \"{s}\", expected one of {}",
+                    Self::ALL.iter().join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct KindInfos {
+    kind_info: Vec<KindInfo>,
+}
+
+impl KindInfos {
+    //The defaults for the link type, with the values given in the options swapped in (or
+    //added). Link types without any kind informations don't get the block at all
+    fn new(link_type: LinkType, values: &[(String, String)]) -> Option<Self> {
+        let mut kind_info: Vec<KindInfo> = link_type
+            .default_kind_infos()
+            .iter()
+            .map(|(name, value)| KindInfo {
+                name: (*name).into(),
+                value: (*value).into(),
+            })
+            .collect();
+        if kind_info.is_empty() {
+            return None;
+        }
+
+        for (name, value) in values {
+            if let Some(info) = kind_info.iter_mut().find(|info| &info.name == name) {
+                info.value.clone_from(value);
+            } else {
+                kind_info.push(KindInfo {
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        Some(KindInfos { kind_info })
+    }
+}
+
+impl From<&KindInfos> for XMLElement {
+    fn from(kinds: &KindInfos) -> Self {
+        let mut kinds_xml = XMLElement::new("kindInformations");
+        for kind in &kinds.kind_info {
+            kinds_xml.add_child(kind.into());
+        }
+
+        kinds_xml
+    }
+}
+
+//e.g. the type of coil a master is, or whether a slave contact is normally open or closed
+#[derive(Debug)]
+pub struct KindInfo {
+    name: String,
+    value: String,
+}
+
+impl From<&KindInfo> for XMLElement {
+    fn from(kind: &KindInfo) -> Self {
+        let mut kind_xml = XMLElement::new("kindInformation");
+        kind_xml.add_attribute("name", &kind.name);
+        kind_xml.add_attribute("show", 1);
+        kind_xml.add_text(&kind.value);
+
+        kind_xml
+    }
+}
+
 #[derive(Debug)]
 pub struct ElemInfos {
     elem_info: Vec<ElemInfo>,
 }
 
+impl ElemInfos {
+    //The values of the attributes mapped to element informations become the defaults of
+    //those informations. If an information is shown by more than one text the first one wins
+    fn from_description(description: &Description) -> Option<Self> {
+        let mut elem_info: Vec<ElemInfo> = Vec::new();
+        let infos = description
+            .objects
+            .iter()
+            .flat_map(|obj| std::iter::once(obj).chain(obj.descendants()))
+            .filter_map(|obj| match obj {
+                Objects::DynamicText(dtext) => dtext.element_info(),
+                _ => None,
+            });
+        for (name, value) in infos {
+            if !elem_info.iter().any(|info| info.name == name) {
+                elem_info.push(ElemInfo {
+                    name: name.into(),
+                    show: 1,
+                    value: value.into(),
+                });
+            }
+        }
+
+        (!elem_info.is_empty()).then_some(ElemInfos { elem_info })
+    }
+}
+
 impl From<&ElemInfos> for XMLElement {
     fn from(elems: &ElemInfos) -> Self {
         let mut elems_xml = XMLElement::new("elementInformations");
@@ -1468,9 +1869,16 @@ impl From<&ElemInfo> for XMLElement {
     }
 }
 
+//The MTEXT text with all of the inline formatting codes taken out
+fn mtext_value(mtxt: &dxf::entities::MText) -> String {
+    mtext::plain_text(&mtext::parse(mtxt).0)
+}
+
 #[inline]
-pub fn two_dec(num: f64) -> f64 {
-    (num * 100.0).round() / 100.0
+//rounds to the given number of decimals
+pub fn round_dec(num: f64, precision: u8) -> f64 {
+    let fact = 10.0_f64.powi(i32::from(precision));
+    (num * fact).round() / fact
 }
 
 //Should be the relevant Qt5 Code for the font string in Qt5...
@@ -1637,4 +2045,5 @@ enum TextEntity<'a> {
     Text(&'a dxf::entities::Text),
     MText(&'a dxf::entities::MText),
     Attrib(&'a AttributeDefinition),
+    Attribute(&'a Attribute),
 }
@@ -0,0 +1,494 @@
+use dxf::entities::MText;
+use dxf::enums::AttachmentPoint;
+use hex_color::HexColor;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{FontInfo, FontStyle};
+
+//Qt5 QFont::Bold, which is what QET expects in the font string
+const QT_BOLD: i32 = 75;
+
+//The formatting that the MTEXT inline codes can switch on and off part way through the text
+//the inline codes are listed at https://ezdxf.readthedocs.io/en/stable/dxfentities/mtext.html
+//Heights are kept as a factor of the MTEXT height, so they work the same for static text
+//(which doesn't use the MTEXT height) and dynamic text
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Format {
+    family: Option<String>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strike_out: bool,
+    height_factor: f64,
+    color: Option<HexColor>,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            family: None,
+            bold: false,
+            italic: false,
+            underline: false,
+            strike_out: false,
+            height_factor: 1.0,
+            color: None,
+        }
+    }
+}
+
+impl Format {
+    pub(super) fn apply(&self, font: &mut FontInfo, color: &mut HexColor) {
+        if let Some(family) = &self.family {
+            font.family.clone_from(family);
+        }
+        if self.bold {
+            font.weight = QT_BOLD;
+        }
+        if self.italic {
+            font.style = FontStyle::Italic;
+        }
+        font.underline |= self.underline;
+        font.strike_out |= self.strike_out;
+        font.point_size *= self.height_factor;
+
+        if let Some(fmt_color) = self.color {
+            *color = fmt_color;
+        }
+    }
+}
+
+//A run of text that has the same formatting all the way through
+#[derive(Debug)]
+pub(crate) struct Span {
+    pub text: String,
+    pub format: Format,
+}
+
+//A part of an MTEXT that can be turned into a single QET text. The offsets are
+//from the MTEXT insertion point in QET coordinates (y going down)
+#[derive(Debug)]
+pub(crate) struct Piece {
+    pub text: String,
+    pub format: Format,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+struct Parser {
+    spans: Vec<Span>,
+    text: String,
+    format: Format,
+
+    //formatting to go back to at the end of each {} group
+    stack: Vec<Format>,
+    height: f64,
+
+    //the codes that were left out, since there's nothing in QET to map them onto
+    ignored: Vec<char>,
+}
+
+impl Parser {
+    //the text so far keeps the old formatting, only what comes after gets the change
+    fn change(&mut self, change: impl FnOnce(&mut Format, f64)) {
+        self.flush();
+        change(&mut self.format, self.height);
+    }
+
+    fn flush(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+
+        let text = std::mem::take(&mut self.text);
+        match self.spans.last_mut() {
+            Some(last) if last.format == self.format => last.text.push_str(&text),
+            _ => self.spans.push(Span {
+                text,
+                format: self.format.clone(),
+            }),
+        }
+    }
+
+    fn ignore(&mut self, code: char) {
+        if !self.ignored.contains(&code) {
+            self.ignored.push(code);
+        }
+    }
+}
+
+//reads the argument of an inline code, which runs up to the next ';'
+fn take_arg(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    chars.by_ref().take_while(|c| *c != ';').collect()
+}
+
+//Joins the MTEXT text fields and splits them up into runs of the same formatting, along with
+//the codes that had to be left out. See the notes in DTextBuilder::build for more detail on the
+//text & extended_text fields
+pub(crate) fn parse(mtxt: &MText) -> (Vec<Span>, Vec<char>) {
+    let mut raw = mtxt.extended_text.join("");
+    raw.push_str(&mtxt.text);
+
+    parse_codes(&raw, mtxt.initial_text_height)
+}
+
+//Splits text with MTEXT inline codes up into runs of the same formatting, dimension
+//texts use the same codes, so this doesn't need an actual MTEXT
+pub(crate) fn parse_str(raw: &str, height: f64) -> Vec<Span> {
+    parse_codes(raw, height).0
+}
+
+#[allow(clippy::too_many_lines)]
+fn parse_codes(raw: &str, height: f64) -> (Vec<Span>, Vec<char>) {
+    let mut parser = Parser {
+        spans: Vec::new(),
+        text: String::new(),
+        format: Format::default(),
+        stack: Vec::new(),
+        height,
+        ignored: Vec::new(),
+    };
+
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => parser.stack.push(parser.format.clone()),
+            '}' => {
+                if let Some(format) = parser.stack.pop() {
+                    parser.change(|fmt, _| *fmt = format);
+                }
+            }
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                match chars.next() {
+                    Some('d' | 'D') => parser.text.push('°'),
+                    Some('p' | 'P') => parser.text.push('±'),
+                    Some('c' | 'C') => parser.text.push('⌀'),
+                    Some('%') => parser.text.push('%'),
+                    Some(other) => {
+                        parser.text.push_str("%%");
+                        parser.text.push(other);
+                    }
+                    None => parser.text.push_str("%%"),
+                }
+            }
+            '\\' => match chars.next() {
+                //paragraph and column breaks
+                Some('P' | 'N') => parser.text.push('\n'),
+                //non breaking space
+                Some('~') => parser.text.push(' '),
+                Some(esc @ ('\\' | '{' | '}')) => parser.text.push(esc),
+                Some('f' | 'F') => {
+                    let arg = take_arg(&mut chars);
+                    let mut params = arg.split('|');
+                    let family = params.next().unwrap_or_default().to_string();
+                    let (mut bold, mut italic) = (false, false);
+                    for param in params {
+                        match param {
+                            "b1" => bold = true,
+                            "i1" => italic = true,
+                            _ => {}
+                        }
+                    }
+                    parser.change(|fmt, _| {
+                        fmt.family = (!family.is_empty()).then_some(family);
+                        fmt.bold = bold;
+                        fmt.italic = italic;
+                    });
+                }
+                Some('H') => {
+                    let arg = take_arg(&mut chars);
+                    //a trailing x means it's relative to the current height
+                    if let Some(rel) = arg.strip_suffix(['x', 'X']) {
+                        if let Ok(rel) = rel.parse::<f64>() {
+                            parser.change(|fmt, _| fmt.height_factor *= rel);
+                        }
+                    } else if let Ok(abs) = arg.parse::<f64>() {
+                        parser.change(|fmt, height| {
+                            if height > 0.0 {
+                                fmt.height_factor = abs / height;
+                            }
+                        });
+                    }
+                }
+                Some('C') => {
+                    if let Ok(index) = take_arg(&mut chars).parse::<u16>() {
+                        parser.change(|fmt, _| fmt.color = aci_color(index));
+                    }
+                }
+                Some('c') => {
+                    //true colors are written out with the red and blue swapped
+                    if let Ok(bgr) = take_arg(&mut chars).parse::<u32>() {
+                        let [_, b, g, r] = bgr.to_be_bytes();
+                        parser.change(|fmt, _| fmt.color = Some(HexColor::rgb(r, g, b)));
+                    }
+                }
+                Some('L') => parser.change(|fmt, _| fmt.underline = true),
+                Some('l') => parser.change(|fmt, _| fmt.underline = false),
+                Some('K') => parser.change(|fmt, _| fmt.strike_out = true),
+                Some('k') => parser.change(|fmt, _| fmt.strike_out = false),
+                //QET has no overline, so it's just left out
+                Some(code @ ('O' | 'o')) => parser.ignore(code),
+                None => {}
+                Some('S') => {
+                    //stacked text (fractions, tolerances) gets written out inline
+                    let arg = take_arg(&mut chars);
+                    if let Some((top, bot)) = arg.split_once(['^', '/', '#']) {
+                        match (top.trim(), bot.trim()) {
+                            (top, "") => parser.text.push_str(top),
+                            ("", bot) => parser.text.push_str(bot),
+                            (top, bot) => {
+                                parser.text.push_str(top);
+                                parser.text.push('/');
+                                parser.text.push_str(bot);
+                            }
+                        }
+                    } else {
+                        parser.text.push_str(&arg);
+                    }
+                }
+                Some('U') if chars.peek() == Some(&'+') => {
+                    chars.next();
+                    let code: String = chars.by_ref().take(4).collect();
+                    if let Some(uc) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                        parser.text.push(uc);
+                    }
+                }
+                //alignment, tracking, oblique angle, width and paragraph codes
+                //don't have anything they can map onto in QET
+                Some(code @ ('A' | 'T' | 'Q' | 'W' | 'p')) => {
+                    take_arg(&mut chars);
+                    parser.ignore(code);
+                }
+                Some(other) => parser.text.push(other),
+            },
+            _ => parser.text.push(c),
+        }
+    }
+    parser.flush();
+
+    (parser.spans, parser.ignored)
+}
+
+pub(crate) fn plain_text(spans: &[Span]) -> String {
+    spans.iter().map(|span| span.text.as_str()).collect()
+}
+
+//When the whole MTEXT has the same formatting it stays in one piece. Otherwise a single QET text
+//can't show it, so it gets split up into a piece for each run on each line. QET doesn't give us the
+//size of the text, so the same guess of the width as the dynamic text alignment is used to place them
+pub(crate) fn layout(spans: Vec<Span>, mtxt: &MText) -> Vec<Piece> {
+    if spans.len() <= 1 {
+        let (text, format) = spans
+            .into_iter()
+            .next()
+            .map(|span| (span.text, span.format))
+            .unwrap_or_default();
+        return vec![Piece {
+            text,
+            format,
+            dx: 0.0,
+            dy: 0.0,
+        }];
+    }
+
+    let mut lines: Vec<Vec<(&str, &Format)>> = vec![Vec::new()];
+    for span in &spans {
+        for (num, part) in span.text.split('\n').enumerate() {
+            if num > 0 {
+                lines.push(Vec::new());
+            }
+            if let Some(line) = lines.last_mut() {
+                if !part.is_empty() {
+                    line.push((part, &span.format));
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let width = |text: &str, fmt: &Format| {
+        text.graphemes(true).count() as f64 * mtxt.initial_text_height * fmt.height_factor * 0.75
+    };
+
+    //the default MTEXT line spacing is 5/3 of the text height
+    let line_height = mtxt.initial_text_height * 5.0 / 3.0 * mtxt.line_spacing_factor;
+    let (sin, cos) = mtxt.rotation_angle.to_radians().sin_cos();
+
+    let mut pieces = Vec::new();
+    for (line_num, line) in lines.iter().enumerate() {
+        let line_width: f64 = line.iter().map(|(text, fmt)| width(text, fmt)).sum();
+        let mut along = match mtxt.attachment_point {
+            AttachmentPoint::TopLeft
+            | AttachmentPoint::MiddleLeft
+            | AttachmentPoint::BottomLeft => 0.0,
+            AttachmentPoint::TopCenter
+            | AttachmentPoint::MiddleCenter
+            | AttachmentPoint::BottomCenter => -line_width / 2.0,
+            AttachmentPoint::TopRight
+            | AttachmentPoint::MiddleRight
+            | AttachmentPoint::BottomRight => -line_width,
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let down = line_num as f64 * line_height;
+
+        for (text, fmt) in line {
+            //rotate the offset along the text direction in the dxf, before flipping the y for QET
+            pieces.push(Piece {
+                text: (*text).to_string(),
+                format: (*fmt).clone(),
+                dx: along * cos + down * sin,
+                dy: -(along * sin - down * cos),
+            });
+            along += width(text, fmt);
+        }
+    }
+
+    pieces
+}
+
+//Turns an AutoCAD Color Index into a color. Index 0 (by block) and 256 (by layer)
+//don't have a color of their own, so they fall back to the entity color
+pub(crate) fn aci_color(index: u16) -> Option<HexColor> {
+    Some(match index {
+        1 => HexColor::rgb(255, 0, 0),
+        2 => HexColor::rgb(255, 255, 0),
+        3 => HexColor::rgb(0, 255, 0),
+        4 => HexColor::rgb(0, 255, 255),
+        5 => HexColor::rgb(0, 0, 255),
+        6 => HexColor::rgb(255, 0, 255),
+        //white on a black background, but QET has a white background
+        7 => HexColor::BLACK,
+        8 => HexColor::rgb(128, 128, 128),
+        9 => HexColor::rgb(192, 192, 192),
+        10..=249 => {
+            //the rest of the colors go around the color wheel in 15° steps, with each
+            //hue in 5 shades, each of which comes in full and half saturation
+            let hue = f64::from((index - 10) / 10) * 15.0;
+            let shade = (index - 10) % 10;
+            let value = [255.0, 165.0, 127.0, 76.0, 38.0][usize::from(shade / 2)];
+            let saturation = if shade % 2 == 0 { 1.0 } else { 0.5 };
+            hsv(hue, saturation, value)
+        }
+        250 => HexColor::rgb(51, 51, 51),
+        251 => HexColor::rgb(80, 80, 80),
+        252 => HexColor::rgb(105, 105, 105),
+        253 => HexColor::rgb(130, 130, 130),
+        254 => HexColor::rgb(190, 190, 190),
+        255 => HexColor::rgb(255, 255, 255),
+        _ => return None,
+    })
+}
+
+//the channels stay between 0 and 255
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn hsv(hue: f64, saturation: f64, value: f64) -> HexColor {
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let min = value - chroma;
+    //the AutoCAD table rounds down, e.g. index 11 is FF7F7F
+    HexColor::rgb((r + min) as u8, (g + min) as u8, (b + min) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(spans: &[Span]) -> Vec<&str> {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn fonts_and_groups() {
+        let spans = parse_str(r"plain\fArial|b1|i1;bold{\H2x;big}back", 2.5);
+        assert_eq!(texts(&spans), ["plain", "bold", "big", "back"]);
+        assert_eq!(spans[0].format, Format::default());
+        assert_eq!(spans[1].format.family.as_deref(), Some("Arial"));
+        assert!(spans[1].format.bold && spans[1].format.italic);
+        assert!((spans[2].format.height_factor - 2.0).abs() < 1e-9);
+        assert!(spans[2].format.bold);
+
+        //the end of the group goes back to the formatting from before it
+        assert_eq!(spans[3].format, spans[1].format);
+    }
+
+    #[test]
+    fn heights() {
+        //an absolute height is kept as a factor of the MTEXT height
+        let spans = parse_str(r"a\H5;b\H0.5x;c", 2.5);
+        assert_eq!(texts(&spans), ["a", "b", "c"]);
+        assert!((spans[1].format.height_factor - 2.0).abs() < 1e-9);
+        assert!((spans[2].format.height_factor - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stacked_text() {
+        assert_eq!(plain_text(&parse_str(r"\S1/2; in", 1.0)), "1/2 in");
+        assert_eq!(plain_text(&parse_str(r"\S+0.1^-0.1;", 1.0)), "+0.1/-0.1");
+        assert_eq!(plain_text(&parse_str(r"x\S^2;", 1.0)), "x2");
+        assert_eq!(plain_text(&parse_str(r"\S12;", 1.0)), "12");
+    }
+
+    #[test]
+    fn special_characters() {
+        assert_eq!(plain_text(&parse_str(r"90\U+00B0", 1.0)), "90°");
+        //not a valid code point, so it's left out
+        assert_eq!(plain_text(&parse_str(r"a\U+D800b", 1.0)), "ab");
+        assert_eq!(
+            plain_text(&parse_str("%%d %%p %%c %%% %%x 5%", 1.0)),
+            "° ± ⌀ % %%x 5%"
+        );
+        assert_eq!(
+            plain_text(&parse_str(r"a\Pb\~c\\d\{e\}", 1.0)),
+            "a\nb c\\d{e}"
+        );
+    }
+
+    #[test]
+    fn unterminated_codes() {
+        //an unclosed group keeps its formatting to the end
+        let spans = parse_str(r"a{\Lb", 1.0);
+        assert_eq!(texts(&spans), ["a", "b"]);
+        assert!(spans[1].format.underline);
+
+        //a stray closing brace is dropped
+        assert_eq!(plain_text(&parse_str("a}b", 1.0)), "ab");
+
+        //a code without its ';' takes the rest of the text as its argument
+        assert_eq!(plain_text(&parse_str(r"x\S1/2", 1.0)), "x1/2");
+        assert_eq!(plain_text(&parse_str(r"x\H2", 1.0)), "x");
+        assert_eq!(plain_text(&parse_str("a\\", 1.0)), "a");
+        assert_eq!(plain_text(&parse_str("%%", 1.0)), "%%");
+        assert_eq!(plain_text(&parse_str(r"\U+4", 1.0)), "\u{4}");
+    }
+
+    #[test]
+    fn ignored_codes() {
+        let (spans, ignored) = parse_codes(r"\A1;\Oover\o\W0.8;\A2;text", 1.0);
+        assert_eq!(plain_text(&spans), "overtext");
+        assert_eq!(ignored, ['A', 'O', 'o', 'W']);
+    }
+
+    #[test]
+    fn colors() {
+        let spans = parse_str(r"\C1;red\c16711680;blue\C256;layer", 1.0);
+        assert_eq!(spans[0].format.color, Some(HexColor::rgb(255, 0, 0)));
+        //BGR, so 0xFF0000 is blue
+        assert_eq!(spans[1].format.color, Some(HexColor::rgb(0, 0, 255)));
+        assert_eq!(spans[2].format.color, None);
+
+        assert_eq!(aci_color(7), Some(HexColor::BLACK));
+        assert_eq!(aci_color(11), Some(HexColor::rgb(255, 127, 127)));
+        assert_eq!(aci_color(0), None);
+    }
+}
@@ -0,0 +1,325 @@
+use dxf::entities::EntityType;
+use dxf::{Point, Vector};
+use std::f64::consts::PI;
+
+//Circles, arcs, 2d polylines, texts, attributes, solids and inserts are stored in the Object Coordinate
+//System (OCS) given by their extrusion direction, instead of the World Coordinate System (WCS)
+//everything else is in. Normally the extrusion is (0, 0, 1) and the two are the same, but
+//mirrored blocks in AutoCAD often end up with an extrusion of (0, 0, -1), where x runs backwards
+pub(crate) struct Ocs {
+    ax: Vector,
+    ay: Vector,
+    az: Vector,
+}
+
+fn cross(v1: &Vector, v2: &Vector) -> Vector {
+    Vector::new(
+        v1.y * v2.z - v1.z * v2.y,
+        v1.z * v2.x - v1.x * v2.z,
+        v1.x * v2.y - v1.y * v2.x,
+    )
+}
+
+fn normalize(v: &Vector) -> Vector {
+    let len = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    Vector::new(v.x / len, v.y / len, v.z / len)
+}
+
+impl Ocs {
+    //None when the OCS is the same as the WCS, which is almost always
+    pub fn new(extrusion: &Vector) -> Option<Self> {
+        let len = (extrusion.x.powi(2) + extrusion.y.powi(2) + extrusion.z.powi(2)).sqrt();
+        if len < 1e-12
+            || (extrusion.x.abs() < 1e-12 && extrusion.y.abs() < 1e-12 && extrusion.z > 0.0)
+        {
+            return None;
+        }
+
+        //the arbitrary axis algorithm from the dxf reference
+        let az = normalize(extrusion);
+        let ax = if az.x.abs() < 1.0 / 64.0 && az.y.abs() < 1.0 / 64.0 {
+            normalize(&cross(&Vector::y_axis(), &az))
+        } else {
+            normalize(&cross(&Vector::z_axis(), &az))
+        };
+        let ay = normalize(&cross(&az, &ax));
+
+        Some(Ocs { ax, ay, az })
+    }
+
+    pub fn point(&self, pt: &Point) -> Point {
+        Point::new(
+            pt.x * self.ax.x + pt.y * self.ay.x + pt.z * self.az.x,
+            pt.x * self.ax.y + pt.y * self.ay.y + pt.z * self.az.y,
+            pt.x * self.ax.z + pt.y * self.ay.z + pt.z * self.az.z,
+        )
+    }
+
+    //the direction of an angle (in degrees) in the OCS, as an angle in the WCS
+    pub fn angle(&self, angle: f64) -> f64 {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let x = cos * self.ax.x + sin * self.ay.x;
+        let y = cos * self.ax.y + sin * self.ay.y;
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    //looking down on the WCS, counter clockwise in the OCS is clockwise in the WCS
+    pub fn mirrored(&self) -> bool {
+        self.ax.x * self.ay.y - self.ax.y * self.ay.x < 0.0
+    }
+}
+
+//The entity with its OCS coordinates turned into WCS coordinates, so the rest of the conversion
+//doesn't need to know about the OCS at all. None if the entity is already in the WCS
+#[allow(clippy::too_many_lines)]
+pub(crate) fn to_wcs(ent: &EntityType) -> Option<EntityType> {
+    match ent {
+        EntityType::Circle(circle) => {
+            let ocs = Ocs::new(&circle.normal)?;
+            let mut circle = circle.clone();
+            circle.center = ocs.point(&circle.center);
+            circle.normal = Vector::z_axis();
+            Some(EntityType::Circle(circle))
+        }
+        EntityType::Arc(arc) => {
+            let ocs = Ocs::new(&arc.normal)?;
+            let mut arc = arc.clone();
+            arc.center = ocs.point(&arc.center);
+
+            //arcs always run counter clockwise, so mirrored arcs run from the end to the start
+            let (start, end) = (ocs.angle(arc.start_angle), ocs.angle(arc.end_angle));
+            (arc.start_angle, arc.end_angle) = if ocs.mirrored() {
+                (end, start)
+            } else {
+                (start, end)
+            };
+            arc.normal = Vector::z_axis();
+            Some(EntityType::Arc(arc))
+        }
+        EntityType::LwPolyline(poly) => {
+            let ocs = Ocs::new(&poly.extrusion_direction)?;
+            let mut poly = poly.clone();
+            //the dxf crate doesn't keep the elevation, it only matters for tilted extrusions anyway
+            for vtx in &mut poly.vertices {
+                let pt = ocs.point(&Point::new(vtx.x, vtx.y, 0.0));
+                (vtx.x, vtx.y) = (pt.x, pt.y);
+                if ocs.mirrored() {
+                    vtx.bulge = -vtx.bulge;
+                }
+            }
+            poly.extrusion_direction = Vector::z_axis();
+            Some(EntityType::LwPolyline(poly))
+        }
+        //3d polylines and meshes are already in the WCS
+        EntityType::Polyline(poly)
+            if !poly.is_3d_polyline() && !poly.is_3d_polygon_mesh() && !poly.is_polyface_mesh() =>
+        {
+            let ocs = Ocs::new(&poly.normal)?;
+            let mut poly = poly.clone();
+            for vtx in poly.vertices_mut() {
+                vtx.location = ocs.point(&vtx.location);
+                if ocs.mirrored() {
+                    vtx.bulge = -vtx.bulge;
+                }
+            }
+            poly.location = ocs.point(&poly.location);
+            poly.normal = Vector::z_axis();
+            Some(EntityType::Polyline(poly))
+        }
+        //QET can't mirror text, so a mirrored text keeps reading the right way
+        //but still runs in the direction it's mirrored to
+        EntityType::Text(text) => {
+            let ocs = Ocs::new(&text.normal)?;
+            let mut text = text.clone();
+            text.location = ocs.point(&text.location);
+            text.second_alignment_point = ocs.point(&text.second_alignment_point);
+            text.rotation = ocs.angle(text.rotation);
+            text.normal = Vector::z_axis();
+            Some(EntityType::Text(text))
+        }
+        EntityType::Attribute(attrib) => {
+            let ocs = Ocs::new(&attrib.normal)?;
+            let mut attrib = attrib.clone();
+            attrib.location = ocs.point(&attrib.location);
+            attrib.second_alignment_point = ocs.point(&attrib.second_alignment_point);
+            attrib.rotation = ocs.angle(attrib.rotation);
+            attrib.normal = Vector::z_axis();
+            Some(EntityType::Attribute(attrib))
+        }
+        EntityType::AttributeDefinition(attdef) => {
+            let ocs = Ocs::new(&attdef.normal)?;
+            let mut attdef = attdef.clone();
+            attdef.location = ocs.point(&attdef.location);
+            attdef.second_alignment_point = ocs.point(&attdef.second_alignment_point);
+            attdef.rotation = ocs.angle(attdef.rotation);
+            attdef.normal = Vector::z_axis();
+            Some(EntityType::AttributeDefinition(attdef))
+        }
+        //the insertion point and x axis of an MTEXT are already in the WCS, only the rotation
+        //angle is in the OCS. The dxf crate fills in the world x axis when the file doesn't
+        //have one, so along with a rotation angle that means it's the angle to go by
+        EntityType::MText(mtext) => {
+            let ocs = Ocs::new(&mtext.extrusion_direction)?;
+            let mut mtext = mtext.clone();
+            let x_axis = &mtext.x_axis_direction;
+            let no_x_axis = (x_axis.x.abs() < 1e-12 && x_axis.y.abs() < 1e-12)
+                || (*x_axis == Vector::x_axis() && mtext.rotation_angle != 0.0);
+            mtext.rotation_angle = if no_x_axis {
+                ocs.angle(mtext.rotation_angle)
+            } else {
+                x_axis.y.atan2(x_axis.x).to_degrees().rem_euclid(360.0)
+            };
+            mtext.x_axis_direction = Vector::zero();
+            mtext.extrusion_direction = Vector::z_axis();
+            Some(EntityType::MText(mtext))
+        }
+        EntityType::Solid(solid) => {
+            let ocs = Ocs::new(&solid.extrusion_direction)?;
+            let mut solid = solid.clone();
+            solid.first_corner = ocs.point(&solid.first_corner);
+            solid.second_corner = ocs.point(&solid.second_corner);
+            solid.third_corner = ocs.point(&solid.third_corner);
+            solid.fourth_corner = ocs.point(&solid.fourth_corner);
+            solid.extrusion_direction = Vector::z_axis();
+            Some(EntityType::Solid(solid))
+        }
+        //the block gets mirrored along with the insert, which is the same as a negative
+        //x scale, with the rotation turned the other way around
+        EntityType::Insert(ins) => {
+            let ocs = Ocs::new(&ins.extrusion_direction)?;
+            let mut ins = ins.clone();
+            ins.location = ocs.point(&ins.location);
+            ins.rotation = ocs.angle(ins.rotation);
+            if ocs.mirrored() {
+                ins.x_scale_factor = -ins.x_scale_factor;
+                ins.rotation = (ins.rotation + 180.0).rem_euclid(360.0);
+            }
+            ins.extrusion_direction = Vector::z_axis();
+            Some(EntityType::Insert(ins))
+        }
+        //ellipses are already in the WCS, but their parameters run counter clockwise
+        //around the extrusion, so they run backwards when it points down
+        EntityType::Ellipse(ellipse) if ellipse.normal.z < 0.0 => {
+            let mut ellipse = ellipse.clone();
+            (ellipse.start_parameter, ellipse.end_parameter) = (
+                2.0 * PI - ellipse.end_parameter,
+                2.0 * PI - ellipse.start_parameter,
+            );
+            ellipse.normal = Vector::z_axis();
+            Some(EntityType::Ellipse(ellipse))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dxf::entities::{Arc, AttributeDefinition, MText};
+
+    fn assert_close(actual: &Point, expected: (f64, f64, f64)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-9
+                && (actual.y - expected.1).abs() < 1e-9
+                && (actual.z - expected.2).abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn same_as_wcs() {
+        assert!(Ocs::new(&Vector::z_axis()).is_none());
+        assert!(Ocs::new(&Vector::new(0.0, 0.0, 2.0)).is_none());
+        assert!(Ocs::new(&Vector::zero()).is_none());
+    }
+
+    #[test]
+    fn pointing_down_mirrors_x() {
+        let ocs = Ocs::new(&Vector::new(0.0, 0.0, -1.0)).unwrap();
+        assert_close(&ocs.point(&Point::new(1.0, 2.0, 3.0)), (-1.0, 2.0, -3.0));
+        assert!((ocs.angle(0.0) - 180.0).abs() < 1e-9);
+        assert!((ocs.angle(90.0) - 90.0).abs() < 1e-9);
+        assert!(ocs.mirrored());
+    }
+
+    #[test]
+    fn arbitrary_axis() {
+        //the x axis of the OCS is the world z axis crossed with the extrusion
+        let ocs = Ocs::new(&Vector::new(1.0, 0.0, 0.0)).unwrap();
+        assert_close(&ocs.point(&Point::new(1.0, 0.0, 0.0)), (0.0, 1.0, 0.0));
+        assert_close(&ocs.point(&Point::new(0.0, 1.0, 0.0)), (0.0, 0.0, 1.0));
+        assert_close(&ocs.point(&Point::new(0.0, 0.0, 1.0)), (1.0, 0.0, 0.0));
+
+        //close to the z axis, the world y axis is used instead
+        let ocs = Ocs::new(&Vector::new(0.01, 0.0, -1.0)).unwrap();
+        assert!(ocs.ax.y.abs() < 1e-9);
+        assert!(ocs.ax.x < 0.0);
+    }
+
+    #[test]
+    fn mirrored_arc_runs_the_other_way() {
+        let mut arc = Arc::new(Point::new(10.0, 0.0, 0.0), 5.0, 0.0, 90.0);
+        arc.normal = Vector::new(0.0, 0.0, -1.0);
+        let Some(EntityType::Arc(arc)) = to_wcs(&EntityType::Arc(arc)) else {
+            panic!("the arc should have been moved to the WCS");
+        };
+
+        //the quarter above and to the right of the center ends up above and to the left
+        assert_close(&arc.center, (-10.0, 0.0, 0.0));
+        assert!((arc.start_angle - 90.0).abs() < 1e-9);
+        assert!((arc.end_angle - 180.0).abs() < 1e-9);
+        assert_eq!(arc.normal, Vector::z_axis());
+    }
+
+    #[test]
+    fn attribute_definition() {
+        let attdef = AttributeDefinition {
+            location: Point::new(4.0, 2.0, 0.0),
+            rotation: 30.0,
+            normal: Vector::new(0.0, 0.0, -1.0),
+            ..Default::default()
+        };
+        let Some(EntityType::AttributeDefinition(attdef)) =
+            to_wcs(&EntityType::AttributeDefinition(attdef))
+        else {
+            panic!("the attribute definition should have been moved to the WCS");
+        };
+        assert_close(&attdef.location, (-4.0, 2.0, 0.0));
+        assert!((attdef.rotation - 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mtext_is_already_in_wcs() {
+        let mtext = MText {
+            insertion_point: Point::new(4.0, 2.0, 0.0),
+            rotation_angle: 30.0,
+            extrusion_direction: Vector::new(0.0, 0.0, -1.0),
+            ..Default::default()
+        };
+        let Some(EntityType::MText(rotated)) = to_wcs(&EntityType::MText(mtext.clone())) else {
+            panic!("the mtext should have been moved to the WCS");
+        };
+        assert_close(&rotated.insertion_point, (4.0, 2.0, 0.0));
+        assert!((rotated.rotation_angle - 150.0).abs() < 1e-9);
+
+        //the x axis wins over the rotation angle
+        let with_x_axis = MText {
+            x_axis_direction: Vector::new(0.0, -1.0, 0.0),
+            ..mtext.clone()
+        };
+        let Some(EntityType::MText(rotated)) = to_wcs(&EntityType::MText(with_x_axis)) else {
+            panic!("the mtext should have been moved to the WCS");
+        };
+        assert!((rotated.rotation_angle - 270.0).abs() < 1e-9);
+
+        //and without either, the text runs along the world x axis
+        let unrotated = MText {
+            rotation_angle: 0.0,
+            ..mtext
+        };
+        let Some(EntityType::MText(rotated)) = to_wcs(&EntityType::MText(unrotated)) else {
+            panic!("the mtext should have been moved to the WCS");
+        };
+        assert!(rotated.rotation_angle.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,157 @@
+use super::{BulgeMode, LinkType, TextMode};
+use uuid::Uuid;
+
+//This gets handed down the whole builder chain, including into nested block inserts,
+//so adding a new knob only means adding a field here
+/// Everything that controls how a drawing gets converted.
+///
+/// New options get added over time, so outside of the crate it has to be built from the
+/// defaults, with the fields changed as needed. That way adding a field doesn't break anyone.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConversionOptions {
+    /// Whether texts become static or dynamic texts.
+    pub text_mode: TextMode,
+
+    /// The number of lines each spline gets broken up into (more lines = greater resolution).
+    pub spline_step: u32,
+
+    /// Whether the arcs of bulged polyline segments become extra polygon points or QET arcs.
+    pub bulge_mode: BulgeMode,
+
+    /// Overrides the scale factor that would otherwise be picked from the drawing units.
+    pub scale: Option<f64>,
+
+    /// Only entities on these layers get converted, all layers if it's empty.
+    pub include_layers: Vec<String>,
+
+    /// Entities on these layers are left out, even if they are in the include list.
+    pub exclude_layers: Vec<String>,
+
+    /// The number of decimals written out for coordinates and sizes.
+    pub precision: u8,
+
+    /// How far off (as a fraction) a closed polyline can be from a perfect circle or
+    /// rectangle and still be converted into an ellipse or rect.
+    pub shape_tolerance: f64,
+
+    /// Pattern hatches are drawn as lines, instead of being filled with the closest QET hatch filling.
+    pub hatch_patterns: bool,
+
+    /// The conversion fails if any entities couldn't be converted, see [`crate::Error::Incomplete`].
+    pub strict: bool,
+
+    /// The dxf entity type names (e.g. "POINT") that are fine to leave out in strict mode.
+    pub allowed_entities: Vec<String>,
+
+    /// Attribute tags (not case sensitive) that fill in one of the QET element informations,
+    /// along with the name of the information. The first match for a tag wins.
+    pub info_names: Vec<(String, String)>,
+
+    /// POINT entities on these layers mark where the terminals go.
+    pub terminal_layers: Vec<String>,
+
+    /// Inserts of these blocks mark where the terminals go, the blocks themselves aren't drawn.
+    pub terminal_blocks: Vec<String>,
+
+    /// How QET links the element to others.
+    pub link_type: LinkType,
+
+    /// The kind informations (e.g. "state" = "NC" for a slave contact) that replace the
+    /// defaults QET starts that type of element off with.
+    pub kind_infos: Vec<(String, String)>,
+
+    /// Translations of the element name, as language code and name pairs. They go on top of the
+    /// english name the element is given, so an "en" name here replaces that one.
+    pub names: Vec<(String, String)>,
+
+    /// Without a namespace every uuid is random. With one they're v5 uuids worked out from the
+    /// namespace, so converting the same drawing with the same namespace gives the same element.
+    pub uuid_namespace: Option<Uuid>,
+}
+
+//the element informations QET knows about, keyed by the attribute tags commonly used for them
+const DEFAULT_INFO_NAMES: [(&str, &str); 15] = [
+    ("LABEL", "label"),
+    ("TAG", "label"),
+    ("DESIGNATION", "designation"),
+    ("DESCRIPTION", "description"),
+    ("COMMENT", "comment"),
+    ("MANUFACTURER", "manufacturer"),
+    ("REF", "manufacturer_reference"),
+    ("MANUFACTURER_REFERENCE", "manufacturer_reference"),
+    (
+        "MACHINE_MANUFACTURER_REFERENCE",
+        "machine_manufacturer_reference",
+    ),
+    ("SUPPLIER", "supplier"),
+    ("QUANTITY", "quantity"),
+    ("UNITY", "unity"),
+    ("PLANT", "plant"),
+    ("LOCATION", "location"),
+    ("FUNCTION", "function"),
+];
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            text_mode: TextMode::default(),
+            spline_step: 20,
+            bulge_mode: BulgeMode::default(),
+            scale: None,
+            include_layers: Vec::new(),
+            exclude_layers: Vec::new(),
+            precision: 2,
+
+            //this boundary of 2% has been chosen arbitrarily
+            //I know in one of my sample files, I'm getting a value of 0.99....
+            shape_tolerance: 0.02,
+            hatch_patterns: false,
+            strict: false,
+            allowed_entities: Vec::new(),
+            info_names: DEFAULT_INFO_NAMES
+                .iter()
+                .map(|(tag, name)| ((*tag).into(), (*name).into()))
+                .collect(),
+            terminal_layers: Vec::new(),
+            terminal_blocks: Vec::new(),
+            link_type: LinkType::default(),
+            kind_infos: Vec::new(),
+            names: Vec::new(),
+            uuid_namespace: None,
+        }
+    }
+}
+
+impl ConversionOptions {
+    /// Whether entities on the layer get converted. Dxf layer names aren't case sensitive,
+    /// so neither are the filters.
+    pub fn includes_layer(&self, layer: &str) -> bool {
+        let matches = |filter: &String| filter.eq_ignore_ascii_case(layer);
+
+        (self.include_layers.is_empty() || self.include_layers.iter().any(matches))
+            && !self.exclude_layers.iter().any(matches)
+    }
+
+    /// The element information an attribute with this tag fills in, if any.
+    pub fn info_name(&self, tag: &str) -> Option<&str> {
+        self.info_names
+            .iter()
+            .find(|(filter, _)| filter.eq_ignore_ascii_case(tag))
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Whether POINT entities on the layer are terminals.
+    pub fn is_terminal_layer(&self, layer: &str) -> bool {
+        self.terminal_layers
+            .iter()
+            .any(|filter| filter.eq_ignore_ascii_case(layer))
+    }
+
+    /// Whether inserts of the block are terminals. Block names aren't case sensitive either.
+    pub fn is_terminal_block(&self, block: &str) -> bool {
+        self.terminal_blocks
+            .iter()
+            .any(|filter| filter.eq_ignore_ascii_case(block))
+    }
+}
@@ -1,7 +1,10 @@
 use crate::qelmt::Bounding;
 
-use super::{two_dec, ScaleEntity};
-use dxf::entities::{LwPolyline, Polyline, Solid, Spline};
+use super::bulge::{sweep, Bulges};
+use super::ellipse;
+use super::transform::{Matrix, Transform};
+use super::{round_dec, ToXml};
+use dxf::entities::{self, LwPolyline, Polyline, Solid, Spline};
 use simple_xml_builder::XMLElement;
 use std::ops::{Add, Mul};
 
@@ -54,16 +57,35 @@ pub struct Polygon {
     closed: bool,
 }
 
+impl Polygon {
+    //a closed polygon filled in with one of the QET fillings (a color, or a hatch like "bdiag")
+    pub(super) fn filled(coordinates: Vec<Coordinate>, filling: &str, color: &str) -> Self {
+        Polygon {
+            coordinates,
+            closed: true,
+            antialias: false,
+            style: format!("line-style:normal;line-weight:thin;filling:{filling};color:{color}"),
+        }
+    }
+
+    //for the shapes that get broken up into points, keeping their own style
+    pub(super) fn with_style(coordinates: Vec<Coordinate>, closed: bool, style: &str) -> Self {
+        Polygon {
+            coordinates,
+            closed,
+            antialias: false,
+            style: style.into(),
+        }
+    }
+}
+
 impl From<&Polyline> for Polygon {
     fn from(poly: &Polyline) -> Self {
         Polygon {
             coordinates: poly
-                .__vertices_and_handles
+                .outline()
                 .iter()
-                .map(|(vertex, _handle)| Coordinate {
-                    x: vertex.location.x,
-                    y: -vertex.location.y,
-                })
+                .map(|pt| Coordinate { x: pt.x, y: -pt.y })
                 .collect(),
             closed: poly.is_closed(),
             //in the original code antialias is always set to false...I'm guessing for performance
@@ -83,12 +105,9 @@ impl From<&LwPolyline> for Polygon {
     fn from(poly: &LwPolyline) -> Self {
         Polygon {
             coordinates: poly
-                .vertices
+                .outline()
                 .iter()
-                .map(|vertex| Coordinate {
-                    x: vertex.x,
-                    y: -vertex.y,
-                })
+                .map(|pt| Coordinate { x: pt.x, y: -pt.y })
                 .collect(),
             closed: poly.is_closed(),
             //in the original code antialias is always set to false...I'm guessing for performance
@@ -127,25 +146,24 @@ impl From<(&Spline, u32)> for Polygon {
             knots,
         );
         let step: f64 =
-            (curr_spline.knot_domain().1 - curr_spline.knot_domain().0) / (spline_step as f64);
+            (curr_spline.knot_domain().1 - curr_spline.knot_domain().0) / f64::from(spline_step);
 
         //there is probably a way to clean up some of this logic and use iterators
         //although it looks like step_by doesn't work on a f64 range...hmmm
         //but I haven't inspected it too closely, and for now am pretty much just duplicating
         //it as antonioaja had it
         let coordinates = {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
             let mut coords = Vec::with_capacity(
                 ((curr_spline.knot_domain().1 - curr_spline.knot_domain().0) / step) as usize + 1,
             );
             let mut j: f64 = curr_spline.knot_domain().0;
-            i = 0;
             while j < curr_spline.knot_domain().1 {
                 coords.push(Coordinate {
                     x: curr_spline.point(j).x,
                     y: -curr_spline.point(j).y,
                 });
                 j += step;
-                i += 1;
             }
             coords
         };
@@ -161,6 +179,45 @@ impl From<(&Spline, u32)> for Polygon {
     }
 }
 
+//rotated ellipses and elliptical arcs can't be QET ellipses or arcs, so they get broken up
+//into points every few degrees of the ellipse parameter
+impl From<&entities::Ellipse> for Polygon {
+    fn from(ellipse: &entities::Ellipse) -> Self {
+        let (major, ratio) = (&ellipse.major_axis, ellipse.minor_axis_ratio);
+        let full = ellipse::is_full(ellipse);
+
+        //going from the start all the way around back to the start is the full ellipse
+        let start = ellipse.start_parameter.to_degrees();
+        let end = if full {
+            start
+        } else {
+            ellipse.end_parameter.to_degrees()
+        };
+
+        let mut coordinates: Vec<Coordinate> = sweep(start, end, true)
+            .map(|param| {
+                let (sin, cos) = param.to_radians().sin_cos();
+                Coordinate {
+                    x: ellipse.center.x + major.x * cos - major.y * ratio * sin,
+                    y: -(ellipse.center.y + major.y * cos + major.x * ratio * sin),
+                }
+            })
+            .collect();
+
+        //a full ellipse is closed, so the last point doesn't need to repeat the first
+        if full {
+            coordinates.pop();
+        }
+
+        Polygon {
+            coordinates,
+            closed: full,
+            antialias: false,
+            style: "line-style:normal;line-weight:thin;filling:none;color:black".into(),
+        }
+    }
+}
+
 impl From<&Solid> for Polygon {
     fn from(solid: &Solid) -> Self {
         Polygon {
@@ -196,13 +253,14 @@ impl From<&Solid> for Polygon {
     }
 }
 
-impl From<&Polygon> for XMLElement {
-    fn from(poly: &Polygon) -> Self {
+impl ToXml for Polygon {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let poly = self;
         let mut poly_xml = XMLElement::new("polygon");
 
         for (count, coord) in poly.coordinates.iter().enumerate() {
-            poly_xml.add_attribute(format!("x{}", (count + 1)), two_dec(coord.x));
-            poly_xml.add_attribute(format!("y{}", (count + 1)), two_dec(coord.y));
+            poly_xml.add_attribute(format!("x{}", (count + 1)), round_dec(coord.x, precision));
+            poly_xml.add_attribute(format!("y{}", (count + 1)), round_dec(coord.y, precision));
         }
 
         //closed defaults to true, don't need to write it out unless it's false
@@ -276,11 +334,10 @@ impl Bounding for Polygon {
     }
 }
 
-impl ScaleEntity for Polygon {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
+impl Transform for Polygon {
+    fn transform(&mut self, matrix: &Matrix) {
         self.coordinates.iter_mut().for_each(|coord| {
-            coord.x *= fact_x;
-            coord.y *= fact_y;
+            (coord.x, coord.y) = matrix.apply(coord.x, coord.y);
         });
     }
 }
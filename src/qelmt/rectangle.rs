@@ -1,7 +1,8 @@
-use super::{two_dec, Bounding, Rectangularity, ScaleEntity};
+use super::polygon::Coordinate;
+use super::transform::{Matrix, Transform};
+use super::{round_dec, Bounding, ErrorKind, Polygon, Rectangularity, ToXml};
 use dxf::entities::{LwPolyline, Polyline};
 use simple_xml_builder::XMLElement;
-use tracing::debug;
 
 #[derive(Debug)]
 pub struct Rectangle {
@@ -19,12 +20,12 @@ pub struct Rectangle {
     antialias: bool,
 }
 
-impl TryFrom<&Polyline> for Rectangle {
-    type Error = &'static str; //add better error type later
+impl TryFrom<(&Polyline, f64)> for Rectangle {
+    type Error = ErrorKind;
 
-    fn try_from(poly: &Polyline) -> Result<Self, Self::Error> {
-        if !poly.is_rectangular() {
-            return Err("Polyline does not appear to be rectangular, can't convert");
+    fn try_from((poly, tolerance): (&Polyline, f64)) -> Result<Self, Self::Error> {
+        if !poly.is_rectangular(tolerance) {
+            return Err(ErrorKind::NotRectangular);
         }
 
         Ok(Rectangle {
@@ -40,12 +41,12 @@ impl TryFrom<&Polyline> for Rectangle {
     }
 }
 
-impl TryFrom<&LwPolyline> for Rectangle {
-    type Error = &'static str; //add better error type later
+impl TryFrom<(&LwPolyline, f64)> for Rectangle {
+    type Error = ErrorKind;
 
-    fn try_from(poly: &LwPolyline) -> Result<Self, Self::Error> {
-        if !poly.is_rectangular() {
-            return Err("LwPolyline does not appear to be rectangular, can't convert");
+    fn try_from((poly, tolerance): (&LwPolyline, f64)) -> Result<Self, Self::Error> {
+        if !poly.is_rectangular(tolerance) {
+            return Err(ErrorKind::NotRectangular);
         }
 
         Ok(Rectangle {
@@ -61,15 +62,16 @@ impl TryFrom<&LwPolyline> for Rectangle {
     }
 }
 
-impl From<&Rectangle> for XMLElement {
-    fn from(rec: &Rectangle) -> Self {
+impl ToXml for Rectangle {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let rec = self;
         let mut rec_xml = XMLElement::new("rect");
-        rec_xml.add_attribute("x", two_dec(rec.x));
-        rec_xml.add_attribute("y", two_dec(rec.y));
-        rec_xml.add_attribute("rx", two_dec(rec.rx));
-        rec_xml.add_attribute("ry", two_dec(rec.ry));
-        rec_xml.add_attribute("height", two_dec(rec.height));
-        rec_xml.add_attribute("width", two_dec(rec.width));
+        rec_xml.add_attribute("x", round_dec(rec.x, precision));
+        rec_xml.add_attribute("y", round_dec(rec.y, precision));
+        rec_xml.add_attribute("rx", round_dec(rec.rx, precision));
+        rec_xml.add_attribute("ry", round_dec(rec.ry, precision));
+        rec_xml.add_attribute("height", round_dec(rec.height, precision));
+        rec_xml.add_attribute("width", round_dec(rec.width, precision));
         rec_xml.add_attribute("antialias", rec.antialias);
         rec_xml.add_attribute("style", &rec.style);
         rec_xml
@@ -94,12 +96,18 @@ impl Bounding for Rectangle {
     }
 }
 
-impl ScaleEntity for Rectangle {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x *= fact_x;
-        self.y *= fact_y;
-        self.width *= fact_x;
-        self.height *= fact_y;
+//Only for matrices that keep the axis lined up, anything else needs to be a polygon instead
+impl Transform for Rectangle {
+    fn transform(&mut self, matrix: &Matrix) {
+        let (x1, y1) = matrix.apply(self.x, self.y);
+        let (x2, y2) = matrix.apply(self.x + self.width, self.y + self.height);
+
+        //the corners might have been swapped around by a mirror or a rotation
+        self.x = x1.min(x2);
+        self.y = y1.min(y2);
+        self.width = (x2 - x1).abs();
+        self.height = (y2 - y1).abs();
+
         // should I be scaling the corner radii?
         // right now they will default to 0, and unless
         // I come up with some way to determine a rounded rectangle
@@ -107,3 +115,25 @@ impl ScaleEntity for Rectangle {
         // I will ever actually use the corner radii
     }
 }
+
+impl From<&Rectangle> for Polygon {
+    fn from(rec: &Rectangle) -> Self {
+        let (right, bottom) = (rec.x + rec.width, rec.y + rec.height);
+        Polygon::with_style(
+            vec![
+                Coordinate { x: rec.x, y: rec.y },
+                Coordinate { x: right, y: rec.y },
+                Coordinate {
+                    x: right,
+                    y: bottom,
+                },
+                Coordinate {
+                    x: rec.x,
+                    y: bottom,
+                },
+            ],
+            true,
+            &rec.style,
+        )
+    }
+}
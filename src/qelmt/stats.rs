@@ -0,0 +1,131 @@
+use dxf::entities::{Entity, EntityType};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use super::error::entity_location;
+use super::{ConversionError, ErrorKind, Objects};
+
+/// What happened to the entities while converting a drawing, so it can be reported back
+/// to the user. This includes the entities inside of any inserted blocks.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ConversionStats {
+    /// Counts of the dxf entities found, keyed by the dxf entity type name.
+    pub entity_counts: BTreeMap<&'static str, usize>,
+
+    /// Counts of the QET objects they were converted into, keyed by the xml element name.
+    pub object_counts: BTreeMap<&'static str, usize>,
+
+    /// Entities of a type that can't be converted yet.
+    pub unsupported: Vec<ConversionError>,
+
+    /// Entities of a supported type that still couldn't be converted.
+    pub dropped: Vec<ConversionError>,
+
+    /// Entities that were converted, but not quite the way they look in the drawing.
+    pub warnings: Vec<String>,
+}
+
+impl ConversionStats {
+    pub(crate) fn count_entity(&mut self, ent: &Entity) {
+        self.count_entity_type(entity_type_name(&ent.specific));
+    }
+
+    //for the entities the dxf crate doesn't know about, like hatches
+    pub(crate) fn count_entity_type(&mut self, entity_type: &'static str) {
+        *self.entity_counts.entry(entity_type).or_default() += 1;
+    }
+
+    pub(crate) fn skip(&mut self, err: ConversionError) {
+        if err.kind == ErrorKind::Unsupported {
+            self.unsupported.push(err);
+        } else {
+            self.dropped.push(err);
+        }
+    }
+
+    pub(crate) fn warn(&mut self, ent: &Entity, block_path: &[String], warning: impl Display) {
+        let location = entity_location(
+            entity_type_name(&ent.specific),
+            &ent.common.handle.as_string(),
+            &ent.common.layer,
+            block_path,
+        );
+        self.warnings.push(format!("{location}: {warning}"));
+    }
+
+    /// Every entity that didn't make it into the element, whatever the reason.
+    pub fn skipped(&self) -> impl Iterator<Item = &ConversionError> {
+        self.unsupported.iter().chain(self.dropped.iter())
+    }
+
+    pub(crate) fn count_objects<'a>(&mut self, objects: impl Iterator<Item = &'a Objects>) {
+        for obj in objects {
+            let name = match obj {
+                Objects::Arc(_) => "arc",
+                Objects::Ellipse(_) => "ellipse",
+                Objects::Polygon(_) => "polygon",
+                Objects::Rectangle(_) => "rect",
+                Objects::DynamicText(_) => "dynamic_text",
+                Objects::Text(_) => "text",
+                Objects::Line(_) => "line",
+                Objects::Terminal(_) => "terminal",
+                //groups don't end up in the element, only their children do
+                Objects::Group(_) => continue,
+            };
+            *self.object_counts.entry(name).or_default() += 1;
+        }
+    }
+}
+
+//The dxf crate has this as to_type_string, but it's only public to the crate
+//so I've copied it here
+pub fn entity_type_name(ent: &EntityType) -> &'static str {
+    match ent {
+        EntityType::Face3D(_) => "3DFACE",
+        EntityType::Solid3D(_) => "3DSOLID",
+        EntityType::ProxyEntity(_) => "ACAD_PROXY_ENTITY",
+        EntityType::Arc(_) => "ARC",
+        EntityType::ArcAlignedText(_) => "ARCALIGNEDTEXT",
+        EntityType::AttributeDefinition(_) => "ATTDEF",
+        EntityType::Attribute(_) => "ATTRIB",
+        EntityType::Body(_) => "BODY",
+        EntityType::Circle(_) => "CIRCLE",
+        EntityType::RotatedDimension(_)
+        | EntityType::RadialDimension(_)
+        | EntityType::DiameterDimension(_)
+        | EntityType::AngularThreePointDimension(_)
+        | EntityType::OrdinateDimension(_) => "DIMENSION",
+        EntityType::Ellipse(_) => "ELLIPSE",
+        EntityType::Helix(_) => "HELIX",
+        EntityType::Image(_) => "IMAGE",
+        EntityType::Insert(_) => "INSERT",
+        EntityType::Leader(_) => "LEADER",
+        EntityType::Light(_) => "LIGHT",
+        EntityType::Line(_) => "LINE",
+        EntityType::LwPolyline(_) => "LWPOLYLINE",
+        EntityType::MLine(_) => "MLINE",
+        EntityType::MText(_) => "MTEXT",
+        EntityType::OleFrame(_) => "OLEFRAME",
+        EntityType::Ole2Frame(_) => "OLE2FRAME",
+        EntityType::ModelPoint(_) => "POINT",
+        EntityType::Polyline(_) => "POLYLINE",
+        EntityType::Ray(_) => "RAY",
+        EntityType::Region(_) => "REGION",
+        EntityType::RText(_) => "RTEXT",
+        EntityType::Section(_) => "SECTION",
+        EntityType::Seqend(_) => "SEQEND",
+        EntityType::Shape(_) => "SHAPE",
+        EntityType::Solid(_) => "SOLID",
+        EntityType::Spline(_) => "SPLINE",
+        EntityType::Text(_) => "TEXT",
+        EntityType::Tolerance(_) => "TOLERANCE",
+        EntityType::Trace(_) => "TRACE",
+        EntityType::DgnUnderlay(_) => "DGNUNDERLAY",
+        EntityType::DwfUnderlay(_) => "DWFUNDERLAY",
+        EntityType::PdfUnderlay(_) => "PDFUNDERLAY",
+        EntityType::Vertex(_) => "VERTEX",
+        EntityType::Wipeout(_) => "WIPEOUT",
+        EntityType::XLine(_) => "XLINE",
+    }
+}
@@ -0,0 +1,168 @@
+use super::transform::{Matrix, Transform};
+use super::{round_dec, Bounding, ToXml};
+use dxf::Point;
+use simple_xml_builder::XMLElement;
+use std::fmt::Display;
+use uuid::Uuid;
+
+//Which side of the element a conductor gets connected to the terminal from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermOrient {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl TermOrient {
+    //the closest orientation to a direction, with the y axis pointing down like in QET
+    fn from_direction(x: f64, y: f64) -> Self {
+        if x.abs() > y.abs() {
+            if x > 0.0 {
+                TermOrient::East
+            } else {
+                TermOrient::West
+            }
+        } else if y > 0.0 {
+            TermOrient::South
+        } else {
+            TermOrient::North
+        }
+    }
+
+    fn direction(self) -> (f64, f64) {
+        match self {
+            TermOrient::North => (0.0, -1.0),
+            TermOrient::East => (1.0, 0.0),
+            TermOrient::South => (0.0, 1.0),
+            TermOrient::West => (-1.0, 0.0),
+        }
+    }
+}
+
+impl Display for TermOrient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TermOrient::North => "n",
+                TermOrient::East => "e",
+                TermOrient::South => "s",
+                TermOrient::West => "w",
+            }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Terminal {
+    pub x: f64,
+    pub y: f64,
+    uuid: Uuid,
+    name: String,
+
+    //a POINT marker doesn't say which way it faces, so it's left open until the whole
+    //element has been converted, and then it faces the closest side of the element
+    orientation: Option<TermOrient>,
+    //type?
+    //  Generic
+    //  Indoor Terminal Block
+    //  External Terminal Block
+}
+
+impl Terminal {
+    //a terminal marked with a POINT, the position is in dxf coordinates
+    //and gets its y flipped around like for every other object
+    pub fn new(x: f64, y: f64) -> Self {
+        Terminal {
+            x,
+            y: -y,
+            uuid: Uuid::new_v4(),
+            name: String::new(),
+            orientation: None,
+        }
+    }
+
+    //A terminal block is drawn with its conductor coming in from the top, at its base point.
+    //The matrix of the insert puts it in place and turns north to wherever the insert has
+    //rotated or mirrored the block to
+    pub(crate) fn from_insert(
+        matrix: &Matrix,
+        base_point: &Point,
+        name: impl Into<String>,
+    ) -> Self {
+        let (x, y) = matrix.apply(base_point.x, -base_point.y);
+        let (dx, dy) = matrix.apply_vector(0.0, -1.0);
+        Terminal {
+            x,
+            y,
+            uuid: Uuid::new_v4(),
+            name: name.into(),
+            orientation: Some(TermOrient::from_direction(dx, dy)),
+        }
+    }
+
+    pub(crate) fn set_uuid(&mut self, uuid: Uuid) {
+        self.uuid = uuid;
+    }
+
+    //faces the side of the element (given by its bounds) the terminal is closest to
+    pub(crate) fn orient_towards(&mut self, left: f64, top: f64, right: f64, bottom: f64) {
+        if self.orientation.is_some() {
+            return;
+        }
+
+        self.orientation = [
+            (self.y - top, TermOrient::North),
+            (right - self.x, TermOrient::East),
+            (bottom - self.y, TermOrient::South),
+            (self.x - left, TermOrient::West),
+        ]
+        .into_iter()
+        .min_by(|(dist1, _), (dist2, _)| dist1.total_cmp(dist2))
+        .map(|(_, orientation)| orientation);
+    }
+}
+
+impl ToXml for Terminal {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let mut term_xml = XMLElement::new("terminal");
+        term_xml.add_attribute("x", round_dec(self.x, precision));
+        term_xml.add_attribute("y", round_dec(self.y, precision));
+        term_xml.add_attribute("orientation", self.orientation.unwrap_or(TermOrient::North));
+        term_xml.add_attribute("uuid", format!("{{{}}}", self.uuid));
+        term_xml.add_attribute("name", &self.name);
+        term_xml.add_attribute("type", "Generic");
+        term_xml
+    }
+}
+
+impl Bounding for Terminal {
+    fn left_bound(&self) -> f64 {
+        self.x
+    }
+
+    fn right_bound(&self) -> f64 {
+        self.x
+    }
+
+    fn top_bound(&self) -> f64 {
+        self.y
+    }
+
+    fn bot_bound(&self) -> f64 {
+        self.y
+    }
+}
+
+impl Transform for Terminal {
+    fn transform(&mut self, matrix: &Matrix) {
+        (self.x, self.y) = matrix.apply(self.x, self.y);
+        if let Some(orientation) = self.orientation {
+            let (x, y) = orientation.direction();
+            let (x, y) = matrix.apply_vector(x, y);
+            self.orientation = Some(TermOrient::from_direction(x, y));
+        }
+    }
+}
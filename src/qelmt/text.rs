@@ -1,9 +1,12 @@
 use crate::qelmt::Bounding;
 
-use super::{two_dec, FontInfo, ScaleEntity};
+use super::mtext::Piece;
+use super::transform::{Matrix, Transform};
+use super::{mtext_value, round_dec, FontInfo, ToXml};
 use dxf::entities;
 use hex_color::HexColor;
 use simple_xml_builder::XMLElement;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug)]
 pub struct Text {
@@ -16,6 +19,7 @@ pub struct Text {
 }
 
 impl From<(&entities::Text, HexColor)> for Text {
+    #[allow(clippy::cast_possible_truncation)]
     fn from((txt, color): (&entities::Text, HexColor)) -> Self {
         Text {
             x: txt.location.x,
@@ -26,23 +30,54 @@ impl From<(&entities::Text, HexColor)> for Text {
                 0.0
             },
             color,
-            font: if &txt.text_style_name == "STANDARD" {
-                FontInfo::default()
-            } else {
-                //txt.text_style_name.clone()
-                FontInfo::default()
+            //the text style isn't looked up yet, only the size is taken over
+            font: FontInfo {
+                point_size: txt.text_height,
+                ..Default::default()
             },
             value: txt.value.clone(),
         }
     }
 }
 
-impl From<&Text> for XMLElement {
-    fn from(txt: &Text) -> Self {
+impl From<(&entities::MText, HexColor)> for Text {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from((mtxt, color): (&entities::MText, HexColor)) -> Self {
+        Text {
+            x: mtxt.insertion_point.x,
+            y: -mtxt.insertion_point.y,
+            rotation: if mtxt.rotation_angle.abs().round() as i64 % 360 != 0 {
+                mtxt.rotation_angle - 180.0
+            } else {
+                0.0
+            },
+            color,
+            font: FontInfo {
+                point_size: mtxt.initial_text_height,
+                ..Default::default()
+            },
+            value: mtext_value(mtxt),
+        }
+    }
+}
+
+impl Text {
+    //swaps in one of the pieces an MTEXT got split into, along with its formatting
+    pub(crate) fn apply_mtext_piece(&mut self, piece: &Piece) {
+        self.value.clone_from(&piece.text);
+        self.x += piece.dx;
+        self.y += piece.dy;
+        piece.format.apply(&mut self.font, &mut self.color);
+    }
+}
+
+impl ToXml for Text {
+    fn to_xml(&self, precision: u8) -> XMLElement {
+        let txt = self;
         let mut txt_xml = XMLElement::new("text");
-        txt_xml.add_attribute("x", two_dec(txt.x));
-        txt_xml.add_attribute("y", two_dec(txt.y));
-        txt_xml.add_attribute("rotation", two_dec(txt.rotation));
+        txt_xml.add_attribute("x", round_dec(txt.x, precision));
+        txt_xml.add_attribute("y", round_dec(txt.y, precision));
+        txt_xml.add_attribute("rotation", round_dec(txt.rotation, precision));
         txt_xml.add_attribute("color", txt.color.display_rgb());
         txt_xml.add_attribute("font", &txt.font);
         txt_xml.add_attribute("text", &txt.value);
@@ -56,25 +91,34 @@ impl Bounding for Text {
     }
 
     fn top_bound(&self) -> f64 {
-        self.y
+        //the y position of a static text is the baseline, so the text sits above it
+        self.y - self.font.point_size
     }
 
+    #[allow(clippy::cast_precision_loss)]
     fn right_bound(&self) -> f64 {
-        //need to be able to measure text size to get this
-        todo!()
+        //need to be able to measure text size to get this properly
+        //for now guess the width from the number of characters and the font size
+        //the same way DynamicText does
+        let longest_line = self
+            .value
+            .lines()
+            .map(|ln| ln.graphemes(true).count())
+            .max()
+            .unwrap_or(0);
+        self.x + (longest_line as f64) * self.font.point_size * 0.75
     }
 
     fn bot_bound(&self) -> f64 {
-        //need to be able to measure text size to get this
-        todo!()
+        self.y
     }
 }
 
-impl ScaleEntity for Text {
-    fn scale(&mut self, fact_x: f64, fact_y: f64) {
-        self.x *= fact_x;
-        self.y *= fact_y;
+impl Transform for Text {
+    fn transform(&mut self, matrix: &Matrix) {
+        (self.x, self.y) = matrix.apply(self.x, self.y);
+        self.rotation += matrix.text_rotation(self.rotation);
         //self.font.pixel_size *= fact;
-        self.font.point_size *= fact_x;
+        self.font.point_size *= matrix.scale_x();
     }
 }
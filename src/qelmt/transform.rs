@@ -0,0 +1,281 @@
+use dxf::entities::Insert;
+use dxf::Point;
+use std::ops::Mul;
+
+//A 2D affine matrix, mapping (x, y) to (a*x + c*y + e, b*x + d*y + f). It can translate,
+//rotate, scale (non uniformly as well) and mirror, and any combination of those
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Matrix {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::identity()
+    }
+}
+
+//self * rhs applies rhs first, and then self
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Matrix {
+        Matrix {
+            a: self.a * rhs.a + self.c * rhs.b,
+            b: self.b * rhs.a + self.d * rhs.b,
+            c: self.a * rhs.c + self.c * rhs.d,
+            d: self.b * rhs.c + self.d * rhs.d,
+            e: self.a * rhs.e + self.c * rhs.f + self.e,
+            f: self.b * rhs.e + self.d * rhs.f + self.f,
+        }
+    }
+}
+
+const EPSILON: f64 = 1e-9;
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Matrix {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Matrix {
+            e: x,
+            f: y,
+            ..Matrix::identity()
+        }
+    }
+
+    //a negative factor mirrors along that axis
+    pub fn scale(x: f64, y: f64) -> Self {
+        Matrix {
+            a: x,
+            d: y,
+            ..Matrix::identity()
+        }
+    }
+
+    //counter clockwise in degrees, with the y axis pointing up
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Matrix {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Matrix::identity()
+        }
+    }
+
+    //Where the entities of a block end up when it's inserted. In the dxf the block gets
+    //moved to its base point, scaled, rotated, and then moved to the insertion point
+    pub fn insert(ins: &Insert, base_point: &Point) -> Self {
+        (Matrix::translate(ins.location.x, ins.location.y)
+            * Matrix::rotate(ins.rotation)
+            * Matrix::scale(ins.x_scale_factor, ins.y_scale_factor)
+            * Matrix::translate(-base_point.x, -base_point.y))
+        .flip_y()
+    }
+
+    //Moves an insert over to one of the cells of a MINSERT array. The rows and columns
+    //run along the rotated x and y axis of the insert, but their spacing isn't scaled
+    pub fn array_cell(ins: &Insert, column: i16, row: i16) -> Self {
+        let (x, y) = Matrix::rotate(ins.rotation).apply_vector(
+            f64::from(column) * ins.column_spacing,
+            f64::from(row) * ins.row_spacing,
+        );
+        Matrix::translate(x, -y)
+    }
+
+    //The objects have their y axis flipped around (pointing down) as soon as they're
+    //converted, so a matrix worked out in dxf coordinates needs flipping before it can be used
+    pub fn flip_y(self) -> Self {
+        Matrix {
+            b: -self.b,
+            c: -self.c,
+            f: -self.f,
+            ..self
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    //like apply, but for a direction or a distance, which don't get translated
+    pub fn apply_vector(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.c * y, self.b * x + self.d * y)
+    }
+
+    //how much lengths along the x and y axis get stretched
+    pub fn scale_x(&self) -> f64 {
+        self.a.hypot(self.b)
+    }
+
+    pub fn scale_y(&self) -> f64 {
+        self.c.hypot(self.d)
+    }
+
+    pub fn is_mirrored(&self) -> bool {
+        self.a * self.d - self.b * self.c < 0.0
+    }
+
+    //the x and y axis still run horizontally and vertically, they might
+    //just be swapped around, flipped or stretched
+    pub fn is_axis_aligned(&self) -> bool {
+        (self.b.abs() < EPSILON && self.c.abs() < EPSILON)
+            || (self.a.abs() < EPSILON && self.d.abs() < EPSILON)
+    }
+
+    //only rotates, mirrors and scales the same in every direction, so circles stay circles
+    pub fn is_uniform(&self) -> bool {
+        (self.scale_x() - self.scale_y()).abs() < EPSILON
+            && (self.a * self.c + self.b * self.d).abs() < EPSILON
+    }
+
+    //QET ellipses, arcs and rectangles can't be rotated, so they only keep their
+    //shape if their axis stay lined up. A circle can be turned any which way though
+    pub fn keeps_shape(&self, circle: bool) -> bool {
+        self.is_axis_aligned() || (circle && self.is_uniform())
+    }
+
+    //the new radii of an ellipse or arc, for a matrix that keeps its shape
+    pub fn radii(&self, rx: f64, ry: f64) -> (f64, f64) {
+        let (ax, ay) = self.apply_vector(rx, 0.0);
+        let (bx, by) = self.apply_vector(0.0, ry);
+        if self.is_axis_aligned() {
+            (ax.abs() + bx.abs(), ay.abs() + by.abs())
+        } else {
+            (ax.hypot(ay), bx.hypot(by))
+        }
+    }
+
+    //How much a text at the given rotation gets turned. QET can't mirror text, so like
+    //AutoCAD does (with MIRRTEXT off), a mirrored text is kept reading from left to right
+    pub fn text_rotation(&self, rotation: f64) -> f64 {
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (x, y) = self.apply_vector(cos, sin);
+        let (x, y) = if self.is_mirrored() && x < 0.0 {
+            (-x, -y)
+        } else {
+            (x, y)
+        };
+
+        let turn = (y.atan2(x).to_degrees() - rotation + 180.0).rem_euclid(360.0) - 180.0;
+        if turn.abs() < EPSILON {
+            0.0
+        } else {
+            turn
+        }
+    }
+}
+
+//Every kind of object can be placed with a matrix
+pub(crate) trait Transform {
+    fn transform(&mut self, matrix: &Matrix);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn multiplying_applies_the_right_hand_side_first() {
+        let matrix = Matrix::translate(10.0, 0.0) * Matrix::rotate(90.0);
+        assert_close(matrix.apply(1.0, 0.0), (10.0, 1.0));
+
+        let matrix = Matrix::rotate(90.0) * Matrix::translate(10.0, 0.0);
+        assert_close(matrix.apply(1.0, 0.0), (0.0, 11.0));
+    }
+
+    #[test]
+    fn vectors_are_not_translated() {
+        let matrix = Matrix::translate(10.0, 20.0) * Matrix::scale(2.0, 3.0);
+        assert_close(matrix.apply(1.0, 1.0), (12.0, 23.0));
+        assert_close(matrix.apply_vector(1.0, 1.0), (2.0, 3.0));
+    }
+
+    #[test]
+    fn insert_around_the_base_point() {
+        let ins = Insert {
+            location: Point::new(100.0, 50.0, 0.0),
+            rotation: 90.0,
+            x_scale_factor: 2.0,
+            y_scale_factor: 2.0,
+            ..Default::default()
+        };
+        let matrix = Matrix::insert(&ins, &Point::new(5.0, 0.0, 0.0));
+
+        //the base point goes to the insertion point, with y flipped
+        assert_close(matrix.apply(5.0, 0.0), (100.0, -50.0));
+        //one unit along x in the block is two units up in the drawing, which is down in QET
+        assert_close(matrix.apply(6.0, 0.0), (100.0, -52.0));
+    }
+
+    #[test]
+    fn flip_y_works_in_qet_coordinates() {
+        let matrix = Matrix::rotate(90.0).flip_y();
+        //(1, 0) is the same in both, and is turned to (0, 1) in dxf, which is (0, -1) in QET
+        assert_close(matrix.apply(1.0, 0.0), (0.0, -1.0));
+        assert!(!matrix.is_mirrored());
+    }
+
+    #[test]
+    fn shapes() {
+        assert!(Matrix::scale(-1.0, 2.0).is_axis_aligned());
+        assert!(Matrix::scale(-1.0, 2.0).is_mirrored());
+        assert!(Matrix::rotate(90.0).is_axis_aligned());
+        assert!(!Matrix::rotate(30.0).is_axis_aligned());
+        assert!(Matrix::rotate(30.0).is_uniform());
+        assert!(!(Matrix::rotate(30.0) * Matrix::scale(1.0, 2.0)).is_uniform());
+
+        assert!(Matrix::rotate(30.0).keeps_shape(true));
+        assert!(!Matrix::rotate(30.0).keeps_shape(false));
+        assert_close(Matrix::rotate(90.0).radii(2.0, 1.0), (1.0, 2.0));
+        assert_close(Matrix::scale(3.0, 3.0).radii(2.0, 1.0), (6.0, 3.0));
+    }
+
+    #[test]
+    fn text_rotation() {
+        assert!((Matrix::rotate(30.0).text_rotation(10.0) - 30.0).abs() < 1e-9);
+        assert!(Matrix::scale(2.0, 2.0).text_rotation(45.0).abs() < 1e-9);
+
+        //mirrored text keeps reading left to right, so a horizontal text stays horizontal
+        assert!(Matrix::scale(-1.0, 1.0).text_rotation(0.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn array_cells_follow_the_rotation() {
+        let ins = Insert {
+            rotation: 90.0,
+            column_spacing: 10.0,
+            row_spacing: 5.0,
+            ..Default::default()
+        };
+        //columns run up, rows run left, and y is flipped for QET
+        assert_close(Matrix::array_cell(&ins, 1, 0).apply(0.0, 0.0), (0.0, -10.0));
+        assert_close(Matrix::array_cell(&ins, 0, 1).apply(0.0, 0.0), (-5.0, 0.0));
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use dxf2elmt::{ConversionError, ConversionStats, Definition};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+//The machine readable version of the --info output, written out with --report
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub elapsed_ms: u128,
+    pub files: Vec<FileReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub error: Option<String>,
+    pub elapsed_ms: u128,
+    pub element: Option<ElementReport>,
+
+    //kept for files that failed in strict mode as well, so the counts and the
+    //entities that were left out are always there once the drawing was converted
+    pub stats: Option<ConversionStats>,
+
+    //the entities that failed the file in strict mode, which are all of the skipped
+    //entities apart from the allowed types
+    pub incomplete: Vec<ConversionError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElementReport {
+    pub width: i64,
+    pub height: i64,
+    pub hotspot_x: i64,
+    pub hotspot_y: i64,
+}
+
+impl From<&Definition> for ElementReport {
+    fn from(def: &Definition) -> Self {
+        let (hotspot_x, hotspot_y) = def.hotspot();
+        ElementReport {
+            width: def.width(),
+            height: def.height(),
+            hotspot_x,
+            hotspot_y,
+        }
+    }
+}
+
+impl BatchReport {
+    /// # Errors
+    ///
+    /// Returns an error if the report file can't be created or written to
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .context(format!("Could not create report file {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .context(format!("Could not write report file {}", path.display()))
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+//runs the dxf2elmt binary on a .dxf, and returns the .elmt it wrote
+fn convert_deterministic(dxf: &Path) -> Vec<u8> {
+    let elmt = dxf.with_extension("elmt");
+    let _ = fs::remove_file(&elmt);
+    let output = Command::new(env!("CARGO_BIN_EXE_dxf2elmt"))
+        .arg(dxf)
+        .args(["--deterministic", "-o"])
+        .arg(&elmt)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::read(elmt).unwrap()
+}
+
+fn uuids(elmt: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(elmt)
+        .split("uuid=\"")
+        .skip(1)
+        .map(|uuid| uuid[..uuid.find('"').unwrap()].to_owned())
+        .collect()
+}
+
+#[test]
+fn same_dxf_gives_the_same_elmt() {
+    let folder =
+        std::env::temp_dir().join(format!("dxf2elmt-deterministic-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    fs::create_dir_all(&folder).unwrap();
+    let dxf = folder.join("symbol.dxf");
+
+    fs::copy(fixture("hatches.dxf"), &dxf).unwrap();
+    let first = convert_deterministic(&dxf);
+    let second = convert_deterministic(&dxf);
+    assert!(!uuids(&first).is_empty());
+    assert!(
+        first == second,
+        "converting the same .dxf twice gave different .elmt files"
+    );
+
+    //a changed drawing at the same path gets new uuids
+    fs::copy(fixture("hatch_extrusion.dxf"), &dxf).unwrap();
+    let changed = convert_deterministic(&dxf);
+    assert!(!uuids(&changed).is_empty());
+    assert_ne!(uuids(&first), uuids(&changed));
+
+    fs::remove_dir_all(&folder).unwrap();
+}
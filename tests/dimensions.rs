@@ -0,0 +1,50 @@
+use dxf::entities::{AngularThreePointDimension, DimensionBase, Entity, EntityType};
+use dxf::{Drawing, Point};
+use dxf2elmt::{convert, ConversionOptions};
+
+//the texts of the dynamic texts in an element
+fn texts(xml: &str) -> Vec<&str> {
+    xml.split("<text>")
+        .skip(1)
+        .map(|txt| &txt[..txt.find("</text>").unwrap()])
+        .collect()
+}
+
+//A right angle at the origin, with no block for its graphics so it gets drawn from the
+//definition points
+fn right_angle(actual_measurement: f64) -> Drawing {
+    let mut drw = Drawing::new();
+    drw.add_entity(Entity::new(EntityType::AngularThreePointDimension(
+        AngularThreePointDimension {
+            dimension_base: DimensionBase {
+                definition_point_1: Point::new(7.0, 7.0, 0.0),
+                text_mid_point: Point::new(8.0, 8.0, 0.0),
+                actual_measurement,
+                ..Default::default()
+            },
+            definition_point_2: Point::new(10.0, 0.0, 0.0),
+            definition_point_3: Point::new(0.0, 10.0, 0.0),
+            definition_point_4: Point::origin(),
+            ..Default::default()
+        },
+    )));
+    drw
+}
+
+#[test]
+fn angular_dimension_shows_its_measurement_in_degrees() {
+    let mut options = ConversionOptions::default();
+    options.scale = Some(1.0);
+
+    //worked out from the definition points
+    let xml = convert("test", &right_angle(0.0), &options)
+        .unwrap()
+        .to_string();
+    assert_eq!(texts(&xml), ["90°"]);
+
+    //written out by the cad program, in radians
+    let xml = convert("test", &right_angle(std::f64::consts::FRAC_PI_4), &options)
+        .unwrap()
+        .to_string();
+    assert_eq!(texts(&xml), ["45°"]);
+}
@@ -0,0 +1,192 @@
+use dxf::entities::{Entity, EntityType, Line};
+use dxf::{Drawing, Point};
+use dxf2elmt::{
+    convert_with_hatches, load, load_file, ConversionOptions, Definition, Error, ErrorKind,
+};
+
+//The hatches are read straight from the group codes of the .dxf file, so these are loaded
+//from actual (ascii) .dxf files. The boundaries are squares, or close to it, so the polygons
+//can be checked by the box around them
+fn convert_fixture_with(name: &str, options: &ConversionOptions) -> Result<Definition, Error> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let (drw, hatches) = load_file(path).unwrap();
+    convert_with_hatches("test", &drw, &hatches, options)
+}
+
+fn options() -> ConversionOptions {
+    let mut options = ConversionOptions::default();
+    options.scale = Some(1.0);
+    options
+}
+
+fn convert_fixture(name: &str) -> Definition {
+    convert_fixture_with(name, &options()).unwrap()
+}
+
+//an attribute of an xml element
+fn attr<'a>(elem: &'a str, name: &str) -> Option<&'a str> {
+    let start = elem.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = elem[start..].find('"')?;
+    Some(&elem[start..start + len])
+}
+
+//the box around each polygon (left, top, right, bottom) and its filling
+fn polygons(def: &Definition) -> Vec<([f64; 4], &'static str)> {
+    let xml = def.to_string();
+    xml.split("<polygon")
+        .skip(1)
+        .map(|elem| {
+            let elem = &elem[..elem.find("/>").unwrap()];
+            let coords = |axis: &str| -> Vec<f64> {
+                (1..)
+                    .map_while(|i| attr(elem, &format!("{axis}{i}")))
+                    .map(|val| val.parse().unwrap())
+                    .collect()
+            };
+            let (xs, ys) = (coords("x"), coords("y"));
+            let bounds = [
+                xs.iter().copied().fold(f64::MAX, f64::min).round(),
+                ys.iter().copied().fold(f64::MAX, f64::min).round(),
+                xs.iter().copied().fold(f64::MIN, f64::max).round(),
+                ys.iter().copied().fold(f64::MIN, f64::max).round(),
+            ];
+            let filling = if attr(elem, "style").unwrap().contains("filling:white") {
+                "white"
+            } else {
+                "black"
+            };
+            (bounds, filling)
+        })
+        .collect()
+}
+
+#[test]
+fn polyline_boundary() {
+    let def = convert_fixture("hatches.dxf");
+    assert_eq!(polygons(&def)[0], ([0.0, -10.0, 10.0, 0.0], "black"));
+}
+
+#[test]
+fn edge_boundaries() {
+    let def = convert_fixture("hatches.dxf");
+    let polygons = polygons(&def);
+
+    //lines and an arc bulging out to the right
+    assert_eq!(polygons[1], ([20.0, -10.0, 35.0, 0.0], "black"));
+    //a whole ellipse, twice as wide as it is high
+    assert_eq!(polygons[2], ([40.0, -8.0, 50.0, -3.0], "black"));
+    //a straight edged spline, going around a triangle
+    assert_eq!(polygons[3], ([60.0, -10.0, 70.0, 0.0], "black"));
+}
+
+#[test]
+fn islands_are_cut_out() {
+    let def = convert_fixture("hatches.dxf");
+    let polygons = polygons(&def);
+    assert_eq!(polygons[4], ([80.0, -20.0, 100.0, 0.0], "black"));
+    assert!(polygons.iter().all(|(_, filling)| *filling != "white"));
+
+    //the island is part of the same polygon, it goes over from a corner of the square to the
+    //closest corner of the island, around it and back again
+    let xml = def.to_string();
+    let square = xml.split("<polygon").nth(5).unwrap();
+    let points: Vec<(&str, &str)> = (1..)
+        .map_while(|i| {
+            Some((
+                attr(square, &format!("x{i}"))?,
+                attr(square, &format!("y{i}"))?,
+            ))
+        })
+        .collect();
+    assert_eq!(points.len(), 10);
+    for corner in [("85", "-5"), ("95", "-5"), ("95", "-15"), ("85", "-15")] {
+        assert!(points.contains(&corner), "{corner:?} isn't in {points:?}");
+    }
+}
+
+#[test]
+fn hatch_in_a_block() {
+    let def = convert_fixture("hatches.dxf");
+    let polygons = polygons(&def);
+
+    //inserted at (200, 0) twice as large
+    assert_eq!(polygons.len(), 6);
+    assert_eq!(polygons[5], ([200.0, -10.0, 210.0, 0.0], "black"));
+    assert_eq!(def.stats().entity_counts.get("HATCH"), Some(&6));
+}
+
+#[test]
+fn unreadable_hatch_is_skipped() {
+    let def = convert_fixture("hatch_unreadable.dxf");
+    assert_eq!(polygons(&def).len(), 1);
+
+    let dropped = &def.stats().dropped;
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].entity_type, "HATCH");
+    assert_eq!(dropped[0].handle.as_string(), "A2");
+    assert_eq!(dropped[0].layer, "BROKEN");
+    assert_eq!(dropped[0].kind, ErrorKind::Unreadable);
+    assert_eq!(def.stats().warnings.len(), 1);
+
+    let mut strict = options();
+    strict.strict = true;
+    match convert_fixture_with("hatch_unreadable.dxf", &strict) {
+        Err(Error::Incomplete { skipped, stats }) => {
+            assert_eq!(skipped, *dropped);
+            assert_eq!(stats.dropped, *dropped);
+            assert_eq!(stats.entity_counts.get("HATCH"), Some(&2));
+        }
+        res => panic!("expected the hatch to fail the conversion, got {res:?}"),
+    }
+}
+
+#[test]
+fn hatches_in_binary_files_are_warned_about() {
+    let mut drw = Drawing::new();
+    drw.add_entity(Entity::new(EntityType::Line(Line::new(
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(10.0, 0.0, 0.0),
+    ))));
+    let mut data = Vec::new();
+    drw.save_binary(&mut data).unwrap();
+
+    let (drw, hatches) = load(&mut data.as_slice()).unwrap();
+    let def = convert_with_hatches("test", &drw, &hatches, &options()).unwrap();
+    assert!(def.stats().warnings.is_empty());
+
+    //the dxf crate can't write hatches, so the line is turned into one. Binary files don't
+    //have any lengths or offsets in them, the loading just skips over the unknown entity
+    let at = data
+        .windows(5)
+        .position(|window| window == b"LINE\0")
+        .unwrap();
+    data.splice(at..at + 4, *b"HATCH");
+    let (drw, hatches) = load(&mut data.as_slice()).unwrap();
+    let def = convert_with_hatches("test", &drw, &hatches, &options()).unwrap();
+    assert_eq!(
+        def.stats().warnings,
+        ["hatches can't be read from binary .dxf files, they were left out"]
+    );
+}
+
+#[test]
+fn spline_edge_without_fit_data() {
+    //an AutoCAD 2004 file, the spline edge is followed by the source boundary objects of the path
+    let def = convert_fixture("hatch_r2004.dxf");
+    assert_eq!(polygons(&def), [([60.0, -10.0, 70.0, 0.0], "black")]);
+    assert!(def.stats().skipped().next().is_none());
+}
+
+#[test]
+fn extrusion_and_elevation() {
+    let def = convert_fixture("hatch_extrusion.dxf");
+    let polygons = polygons(&def);
+
+    //an extrusion pointing down mirrors the boundaries, so x runs backwards
+    assert_eq!(polygons[0], ([-10.0, -10.0, 0.0, 0.0], "black"));
+    assert_eq!(polygons[1], ([-35.0, -10.0, -20.0, 0.0], "black"));
+
+    //tilted 45° around the y axis, so it's squashed in x and the elevation moves it sideways,
+    //without the elevation it would go from -7 to 0
+    assert_eq!(polygons[2], ([-4.0, -10.0, 4.0, 0.0], "black"));
+}
@@ -0,0 +1,361 @@
+use dxf::entities::{Attribute, AttributeDefinition, Entity, EntityType, Insert, Line};
+use dxf::{Block, Drawing, Point};
+use dxf2elmt::{convert, load_file, ConversionOptions, Error, ErrorKind};
+
+//The blocks are mostly built in code rather than loaded from .dxf files, so the expected
+//positions can be worked out straight from the fixture
+
+fn options() -> ConversionOptions {
+    //no unit scaling, so the element coordinates are the dxf coordinates with y flipped
+    let mut options = ConversionOptions::default();
+    options.scale = Some(1.0);
+    options.precision = 3;
+    options
+}
+
+fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Entity {
+    Entity::new(EntityType::Line(Line::new(
+        Point::new(x1, y1, 0.0),
+        Point::new(x2, y2, 0.0),
+    )))
+}
+
+fn block(name: &str, base_point: Point, entities: Vec<Entity>) -> Block {
+    Block {
+        name: name.into(),
+        base_point,
+        entities,
+        ..Default::default()
+    }
+}
+
+fn insert(name: &str, x: f64, y: f64) -> Insert {
+    Insert {
+        name: name.into(),
+        location: Point::new(x, y, 0.0),
+        ..Default::default()
+    }
+}
+
+fn insert_entity(ins: Insert) -> Entity {
+    Entity::new(EntityType::Insert(ins))
+}
+
+//a number attribute of an xml element
+fn attr(elem: &str, name: &str) -> f64 {
+    let start = elem.find(&format!(" {name}=\"")).unwrap() + name.len() + 3;
+    let len = elem[start..].find('"').unwrap();
+    elem[start..start + len].parse::<f64>().unwrap()
+}
+
+//the x1, y1, x2, y2 of every line in the element
+fn lines(drw: &Drawing) -> Vec<[f64; 4]> {
+    let xml = convert("test", drw, &options()).unwrap().to_string();
+    xml.split("<line")
+        .skip(1)
+        .map(|elem| {
+            [
+                attr(elem, "x1"),
+                attr(elem, "y1"),
+                attr(elem, "x2"),
+                attr(elem, "y2"),
+            ]
+        })
+        .collect()
+}
+
+fn assert_lines(found: &[[f64; 4]], expected: &[[f64; 4]]) {
+    assert_eq!(found.len(), expected.len(), "found lines {found:?}");
+    for (found, expected) in found.iter().zip(expected) {
+        assert!(
+            found
+                .iter()
+                .zip(expected)
+                .all(|(fnd, exp)| (fnd - exp).abs() < 1e-3),
+            "expected line {expected:?}, found {found:?}"
+        );
+    }
+}
+
+#[test]
+fn top_level_insert_subtracts_base_point() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "A",
+        Point::new(5.0, 5.0, 0.0),
+        vec![line(5.0, 5.0, 15.0, 5.0)],
+    ));
+    drw.add_entity(insert_entity(insert("A", 100.0, 0.0)));
+
+    assert_lines(&lines(&drw), &[[100.0, 0.0, 110.0, 0.0]]);
+}
+
+#[test]
+fn nested_insert_applies_base_point_scale_rotation_then_location() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "INNER",
+        Point::new(1.0, 0.0, 0.0),
+        vec![line(1.0, 0.0, 3.0, 0.0)],
+    ));
+    drw.add_block(block(
+        "OUTER",
+        Point::origin(),
+        vec![insert_entity(Insert {
+            rotation: 90.0,
+            ..insert("INNER", 10.0, 0.0)
+        })],
+    ));
+    drw.add_entity(insert_entity(Insert {
+        x_scale_factor: 2.0,
+        y_scale_factor: 2.0,
+        ..insert("OUTER", 100.0, 100.0)
+    }));
+
+    //(1,0)-(3,0) -> base point (0,0)-(2,0) -> rotated (0,0)-(0,2) -> in OUTER (10,0)-(10,2)
+    //-> scaled (20,0)-(20,4) -> inserted (120,100)-(120,104)
+    assert_lines(&lines(&drw), &[[120.0, -100.0, 120.0, -104.0]]);
+}
+
+#[test]
+fn deeply_nested_inserts_add_up() {
+    let mut drw = Drawing::new();
+    for level in 0..5 {
+        let entities = if level == 4 {
+            vec![line(0.0, 0.0, 1.0, 0.0)]
+        } else {
+            vec![insert_entity(insert(&format!("L{}", level + 1), 1.0, 0.0))]
+        };
+        drw.add_block(block(&format!("L{level}"), Point::origin(), entities));
+    }
+    drw.add_entity(insert_entity(insert("L0", 0.0, 10.0)));
+
+    assert_lines(&lines(&drw), &[[4.0, -10.0, 5.0, -10.0]]);
+}
+
+#[test]
+fn rotated_insert_turns_block_around_insertion_point() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "A",
+        Point::origin(),
+        vec![line(0.0, 0.0, 10.0, 0.0), line(0.0, 0.0, 0.0, 5.0)],
+    ));
+    drw.add_entity(insert_entity(Insert {
+        rotation: 180.0,
+        ..insert("A", 50.0, 50.0)
+    }));
+
+    assert_lines(
+        &lines(&drw),
+        &[[50.0, -50.0, 40.0, -50.0], [50.0, -50.0, 50.0, -45.0]],
+    );
+}
+
+#[test]
+fn mirrored_insert_flips_block() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "A",
+        Point::new(2.0, 0.0, 0.0),
+        vec![line(2.0, 0.0, 12.0, 0.0)],
+    ));
+    drw.add_entity(insert_entity(Insert {
+        x_scale_factor: -1.0,
+        ..insert("A", 50.0, 0.0)
+    }));
+
+    assert_lines(&lines(&drw), &[[50.0, 0.0, 40.0, 0.0]]);
+}
+
+#[test]
+fn recursive_block_is_reported_instead_of_nesting_forever() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "R",
+        Point::origin(),
+        vec![
+            line(0.0, 0.0, 1.0, 0.0),
+            insert_entity(insert("R", 5.0, 0.0)),
+        ],
+    ));
+    drw.add_entity(insert_entity(insert("R", 0.0, 0.0)));
+
+    assert_lines(&lines(&drw), &[[0.0, 0.0, 1.0, 0.0]]);
+
+    let mut strict = options();
+    strict.strict = true;
+    match convert("test", &drw, &strict) {
+        Err(Error::Incomplete { skipped, .. }) => {
+            assert_eq!(skipped.len(), 1);
+            assert_eq!(skipped[0].kind, ErrorKind::RecursiveBlock("R".into()));
+            assert_eq!(skipped[0].block_path, ["R"]);
+        }
+        res => panic!("expected the recursive insert to be skipped, got {res:?}"),
+    }
+}
+
+//The size of the element has to take in the far side of the objects, not just where they start,
+//otherwise a block of long lines ends up in a tiny element
+#[test]
+fn element_covers_the_whole_inserted_block() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "CORNER",
+        Point::origin(),
+        vec![line(0.0, 0.0, 100.0, 0.0), line(0.0, 0.0, 0.0, 100.0)],
+    ));
+    drw.add_entity(insert_entity(insert("CORNER", 50.0, 50.0)));
+
+    let def = convert("test", &drw, &options()).unwrap();
+    assert_eq!((def.width(), def.height()), (110, 110));
+    assert_eq!(def.hotspot(), (-45, 155));
+}
+
+#[test]
+fn filtered_out_block_leaves_the_element_size_alone() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "CORNER",
+        Point::origin(),
+        vec![line(0.0, 0.0, 100.0, 0.0), line(0.0, 0.0, 0.0, 100.0)],
+    ));
+    //everything in NOTES is on an excluded layer, including what's in the block nested in it
+    let mut note = line(-500.0, -500.0, -400.0, -500.0);
+    note.common.layer = "NOTES".into();
+    drw.add_block(block("INNER", Point::origin(), vec![note.clone()]));
+    drw.add_block(block(
+        "NOTES",
+        Point::origin(),
+        vec![note, insert_entity(insert("INNER", 0.0, 0.0))],
+    ));
+    drw.add_entity(insert_entity(insert("CORNER", 50.0, 50.0)));
+    drw.add_entity(insert_entity(insert("NOTES", 0.0, 0.0)));
+
+    let mut options = options();
+    options.exclude_layers = vec!["NOTES".into()];
+    let def = convert("test", &drw, &options).unwrap();
+    assert_eq!((def.width(), def.height()), (110, 110));
+    assert_eq!(def.hotspot(), (-45, 155));
+}
+
+#[test]
+fn array_insert_repeats_block_along_rotated_rows_and_columns() {
+    let mut drw = Drawing::new();
+    drw.add_block(block(
+        "TERMINAL",
+        Point::origin(),
+        vec![line(0.0, 0.0, 1.0, 0.0)],
+    ));
+    drw.add_entity(insert_entity(Insert {
+        rotation: 90.0,
+        column_count: 3,
+        row_count: 2,
+        column_spacing: 5.0,
+        row_spacing: 10.0,
+        ..insert("TERMINAL", 10.0, 0.0)
+    }));
+
+    //turned 90 degrees, so the columns go up and the rows go to the left
+    assert_lines(
+        &lines(&drw),
+        &[
+            [10.0, 0.0, 10.0, -1.0],
+            [10.0, -5.0, 10.0, -6.0],
+            [10.0, -10.0, 10.0, -11.0],
+            [0.0, 0.0, 0.0, -1.0],
+            [0.0, -5.0, 0.0, -6.0],
+            [0.0, -10.0, 0.0, -11.0],
+        ],
+    );
+}
+
+//The array settings of a MINSERT are read from group codes 70, 71, 44 and 45 of the INSERT,
+//so this one is loaded from a .dxf file to make sure they make it through
+#[test]
+fn array_insert_from_a_dxf_file() {
+    let path = format!("{}/tests/fixtures/minsert.dxf", env!("CARGO_MANIFEST_DIR"));
+    let (drw, _) = load_file(path).unwrap();
+    let ins = drw
+        .entities()
+        .find_map(|ent| match &ent.specific {
+            EntityType::Insert(ins) => Some(ins),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!((ins.column_count, ins.row_count), (3, 2));
+    assert_eq!((ins.column_spacing, ins.row_spacing), (10.0, 5.0));
+
+    assert_lines(
+        &lines(&drw),
+        &[
+            [100.0, 0.0, 102.0, 0.0],
+            [110.0, 0.0, 112.0, 0.0],
+            [120.0, 0.0, 122.0, 0.0],
+            [100.0, -5.0, 102.0, -5.0],
+            [110.0, -5.0, 112.0, -5.0],
+            [120.0, -5.0, 122.0, -5.0],
+        ],
+    );
+}
+
+#[test]
+fn insert_attributes_replace_their_attribute_definitions() {
+    let mut drw = Drawing::new();
+    let attdef = |tag: &str, value: &str, flags: i32| {
+        Entity::new(EntityType::AttributeDefinition(AttributeDefinition {
+            text_tag: tag.into(),
+            value: value.into(),
+            flags,
+            ..Default::default()
+        }))
+    };
+    drw.add_block(block(
+        "RELAY",
+        Point::origin(),
+        vec![
+            line(0.0, 0.0, 1.0, 0.0),
+            attdef("TAG", "K?", 0),
+            //constant attribute definitions never get an attribute on the insert
+            attdef("KIND", "RELAY", 2),
+        ],
+    ));
+
+    let mut ins = insert("RELAY", 10.0, 0.0);
+    for (tag, value, flags) in [("TAG", "K1", 0), ("NOTE", "hidden", 1)] {
+        ins.add_attribute(
+            &mut drw,
+            Attribute {
+                attribute_tag: tag.into(),
+                value: value.into(),
+                flags,
+                location: Point::new(12.0, 3.0, 0.0),
+                ..Default::default()
+            },
+        );
+    }
+    drw.add_entity(insert_entity(ins));
+
+    let xml = convert("test", &drw, &options()).unwrap().to_string();
+    let texts: Vec<(f64, f64, &str)> = xml
+        .split("<dynamic_text")
+        .skip(1)
+        .map(|elem| {
+            let text = elem.split("<text>").nth(1).unwrap();
+            (
+                attr(elem, "x"),
+                attr(elem, "y"),
+                text.split("</text>").next().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        texts.iter().map(|txt| txt.2).collect::<Vec<_>>(),
+        ["RELAY", "K1"]
+    );
+
+    //the attribute is already in drawing coordinates, so it doesn't get moved along with the
+    //block like the definition at the block origin does
+    let (relay, k1) = (texts[0], texts[1]);
+    assert!((k1.0 - relay.0 - 2.0).abs() < 1e-3 && (k1.1 - relay.1 + 3.0).abs() < 1e-3);
+}
@@ -0,0 +1,80 @@
+use dxf::entities::{Entity, EntityType, Line};
+use dxf::{Drawing, Point};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+//runs dxf2elmt with the .dxf data piped into stdin
+fn run_piped(dxf: &[u8], args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dxf2elmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(dxf).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn fixture() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/minsert.dxf")).unwrap()
+}
+
+//stdout has to be nothing but the element, so it can be piped on into a file or another program
+fn assert_only_xml(stdout: &[u8]) {
+    let stdout = String::from_utf8_lossy(stdout);
+    assert!(stdout.starts_with("<?xml"), "{stdout}");
+    assert!(stdout.trim_end().ends_with("</definition>"), "{stdout}");
+    assert_eq!(stdout.matches("<definition").count(), 1);
+}
+
+#[test]
+fn stdin_to_stdout() {
+    let output = run_piped(&fixture(), &["-", "-o", "-", "--info"]);
+    assert_only_xml(&output.stdout);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(r#"<name lang="en">stdin</name>"#));
+
+    //the chatter all goes to stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("stdin loaded..."), "{stderr}");
+    assert!(stderr.contains("Converted 1 of 1 files"), "{stderr}");
+}
+
+#[test]
+fn binary_dxf_from_stdin() {
+    let mut drw = Drawing::new();
+    drw.add_entity(Entity::new(EntityType::Line(Line::new(
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(10.0, 0.0, 0.0),
+    ))));
+    let mut dxf = Vec::new();
+    drw.save_binary(&mut dxf).unwrap();
+
+    let output = run_piped(&dxf, &["-", "-o", "-"]);
+    assert_only_xml(&output.stdout);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("<line "));
+}
+
+#[test]
+fn stdin_named_after_the_output_file() {
+    let folder = std::env::temp_dir().join(format!("dxf2elmt-pipe-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    let elmt = folder.join("K1_coil.elmt");
+
+    let output = run_piped(&fixture(), &["-", "-o", elmt.to_str().unwrap()]);
+    //not writing to stdout, so the usual messages go there instead
+    assert!(String::from_utf8_lossy(&output.stdout).contains("was created"));
+
+    let xml = fs::read_to_string(&elmt).unwrap();
+    assert!(xml.contains(r#"<name lang="en">K1_coil</name>"#), "{xml}");
+
+    fs::remove_dir_all(&folder).unwrap();
+}
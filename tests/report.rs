@@ -0,0 +1,100 @@
+use dxf::entities::{Ellipse, Entity, EntityType, Line, XLine};
+use dxf::enums::AcadVersion;
+use dxf::{Drawing, Point, Vector};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn test_folder() -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("dxf2elmt-report-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+//a line, an XLINE which can't be converted, and a rotated ellipse which gets approximated
+fn drawing() -> Drawing {
+    let mut drw = Drawing::new();
+    //R12, the default, doesn't have XLINE and ELLIPSE entities
+    drw.header.version = AcadVersion::R2004;
+    drw.add_entity(Entity::new(EntityType::Line(Line::new(
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(40.0, 30.0, 0.0),
+    ))));
+    let mut xline = Entity::new(EntityType::XLine(XLine::default()));
+    xline.common.layer = "CONSTRUCTION".into();
+    drw.add_entity(xline);
+    drw.add_entity(Entity::new(EntityType::Ellipse(Ellipse {
+        center: Point::new(20.0, 15.0, 0.0),
+        major_axis: Vector::new(5.0, 5.0, 0.0),
+        minor_axis_ratio: 0.5,
+        start_parameter: 0.0,
+        end_parameter: std::f64::consts::TAU,
+        ..Default::default()
+    })));
+    drw
+}
+
+#[test]
+fn report_lists_every_file() {
+    let folder = test_folder();
+    let good = folder.join("good.dxf");
+    drawing().save_file(&good).unwrap();
+    let bad = folder.join("bad.dxf");
+    fs::write(&bad, "not a dxf file").unwrap();
+    let report_file = folder.join("report.json");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_dxf2elmt"))
+        .args([&good, &bad])
+        .args(["--scale", "1", "--report"])
+        .arg(&report_file)
+        .output()
+        .unwrap()
+        .status;
+    assert!(!status.success());
+
+    let report: Value = serde_json::from_str(&fs::read_to_string(&report_file).unwrap()).unwrap();
+    assert_eq!(report["succeeded"], 1);
+    assert_eq!(report["failed"], 1);
+
+    let good = &report["files"][0];
+    assert!(good["input"].as_str().unwrap().ends_with("good.dxf"));
+    assert!(good["output"].as_str().unwrap().ends_with("good.elmt"));
+    assert!(good["error"].is_null());
+    assert_eq!(good["element"]["width"], 50);
+    assert_eq!(good["element"]["height"], 40);
+
+    let stats = &good["stats"];
+    assert_eq!(stats["entity_counts"]["LINE"], 1);
+    assert_eq!(stats["entity_counts"]["XLINE"], 1);
+    assert_eq!(stats["object_counts"]["line"], 1);
+    assert_eq!(stats["object_counts"]["polygon"], 1);
+
+    let unsupported = stats["unsupported"].as_array().unwrap();
+    assert_eq!(unsupported.len(), 1);
+    assert_eq!(unsupported[0]["entity_type"], "XLINE");
+    assert_eq!(unsupported[0]["layer"], "CONSTRUCTION");
+    assert_eq!(unsupported[0]["kind"], "Unsupported");
+    assert!(unsupported[0]["handle"].is_string());
+    assert!(stats["dropped"].as_array().unwrap().is_empty());
+
+    let warnings = stats["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0]
+        .as_str()
+        .unwrap()
+        .ends_with("rotated ellipse drawn as a polygon"));
+
+    //a file that can't be loaded has nothing to report but the error
+    let bad = &report["files"][1];
+    assert!(bad["output"].is_null());
+    assert!(bad["error"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to load bad"));
+    assert!(bad["element"].is_null());
+    assert!(bad["stats"].is_null());
+
+    fs::remove_dir_all(&folder).unwrap();
+}
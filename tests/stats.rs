@@ -0,0 +1,73 @@
+use dxf::entities::{Ellipse, Entity, EntityType, Line, MText};
+use dxf::{Drawing, Point, Vector};
+use dxf2elmt::{convert, ConversionOptions};
+
+fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Entity {
+    Entity::new(EntityType::Line(Line::new(
+        Point::new(x1, y1, 0.0),
+        Point::new(x2, y2, 0.0),
+    )))
+}
+
+//an outline 40 wide and 30 high, with its bottom left corner at (100, 100)
+fn outline() -> Drawing {
+    let mut drw = Drawing::new();
+    for (x1, y1, x2, y2) in [
+        (100.0, 100.0, 140.0, 100.0),
+        (140.0, 100.0, 140.0, 130.0),
+        (140.0, 130.0, 100.0, 130.0),
+        (100.0, 130.0, 100.0, 100.0),
+    ] {
+        drw.add_entity(line(x1, y1, x2, y2));
+    }
+    drw
+}
+
+fn options() -> ConversionOptions {
+    let mut options = ConversionOptions::default();
+    options.scale = Some(1.0);
+    options
+}
+
+#[test]
+fn element_size_and_hotspot_follow_the_drawing() {
+    let def = convert("test", &outline(), &options()).unwrap();
+
+    //rounded up to the next 10 with a margin around the drawing, like QET does it
+    assert_eq!((def.width(), def.height()), (50, 40));
+    assert_eq!(def.hotspot(), (-95, 135));
+    assert_eq!(def.stats().entity_counts.get("LINE"), Some(&4));
+    assert_eq!(def.stats().object_counts.get("line"), Some(&4));
+    assert!(def.stats().warnings.is_empty());
+}
+
+#[test]
+fn approximations_are_warned_about() {
+    let mut drw = outline();
+    drw.add_entity(Entity::new(EntityType::Ellipse(Ellipse {
+        center: Point::new(120.0, 115.0, 0.0),
+        major_axis: Vector::new(5.0, 5.0, 0.0),
+        minor_axis_ratio: 0.5,
+        start_parameter: 0.0,
+        end_parameter: std::f64::consts::TAU,
+        ..Default::default()
+    })));
+    drw.add_entity(Entity::new(EntityType::MText(MText {
+        insertion_point: Point::new(105.0, 110.0, 0.0),
+        initial_text_height: 2.5,
+        text: "\\A1;\\OK1\\o".into(),
+        ..Default::default()
+    })));
+
+    let def = convert("test", &drw, &options()).unwrap();
+    let warnings = &def.stats().warnings;
+    assert_eq!(warnings.len(), 2, "{warnings:?}");
+    assert!(warnings[0].starts_with("ELLIPSE (handle "));
+    assert!(warnings[0].ends_with("rotated ellipse drawn as a polygon"));
+    assert!(warnings[1].starts_with("MTEXT (handle "));
+    assert!(warnings[1].contains("\\A, \\O, \\o"));
+
+    //the approximations are inside of the outline, so they don't change the size
+    assert_eq!((def.width(), def.height()), (50, 40));
+    assert!(def.stats().skipped().next().is_none());
+}
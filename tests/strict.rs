@@ -0,0 +1,65 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+//has a readable hatch, and one on the BROKEN layer with a boundary that can't be read
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hatch_unreadable.dxf")
+}
+
+//converts the fixture into the folder in strict mode, returning how it exited and its report
+fn convert_strict(folder: &Path, extra_args: &[&str]) -> (ExitStatus, Value) {
+    let report_file = folder.join("report.json");
+    let status = Command::new(env!("CARGO_BIN_EXE_dxf2elmt"))
+        .arg(fixture())
+        .arg("--strict")
+        .args(extra_args)
+        .arg("--output-dir")
+        .arg(folder)
+        .arg("--report")
+        .arg(&report_file)
+        .output()
+        .unwrap()
+        .status;
+    let report = serde_json::from_str(&fs::read_to_string(&report_file).unwrap()).unwrap();
+    (status, report)
+}
+
+#[test]
+fn strict_mode_lists_the_entities_that_failed_the_file() {
+    let folder = std::env::temp_dir().join(format!("dxf2elmt-strict-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    fs::create_dir_all(&folder).unwrap();
+    let elmt = folder.join("hatch_unreadable.elmt");
+
+    let (status, report) = convert_strict(&folder, &[]);
+    assert!(!status.success());
+    assert!(!elmt.exists());
+
+    let file = &report["files"][0];
+    assert!(file["output"].is_null());
+    let incomplete = file["incomplete"].as_array().unwrap();
+    assert_eq!(incomplete.len(), 1);
+    assert_eq!(incomplete[0]["entity_type"], "HATCH");
+    assert_eq!(incomplete[0]["handle"], "A2");
+    assert_eq!(incomplete[0]["layer"], "BROKEN");
+    assert_eq!(incomplete[0]["kind"], "Unreadable");
+    assert!(incomplete[0]["block_path"].as_array().unwrap().is_empty());
+
+    //the counts are there as well, even though the file failed
+    assert_eq!(file["stats"]["entity_counts"]["HATCH"], 2);
+    assert_eq!(file["stats"]["object_counts"]["polygon"], 1);
+    assert_eq!(file["stats"]["dropped"], file["incomplete"]);
+
+    //allowing hatches to be left out lets the file through
+    let (status, report) = convert_strict(&folder, &["--allow", "HATCH"]);
+    assert!(status.success());
+    assert!(elmt.exists());
+    let file = &report["files"][0];
+    assert!(file["error"].is_null());
+    assert!(file["incomplete"].as_array().unwrap().is_empty());
+    assert_eq!(file["stats"]["dropped"][0]["handle"], "A2");
+
+    fs::remove_dir_all(&folder).unwrap();
+}
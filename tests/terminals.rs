@@ -0,0 +1,71 @@
+use dxf::entities::{Entity, EntityType, Line, ModelPoint};
+use dxf::{Drawing, Point};
+use dxf2elmt::{convert, ConversionOptions};
+
+fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Entity {
+    Entity::new(EntityType::Line(Line::new(
+        Point::new(x1, y1, 0.0),
+        Point::new(x2, y2, 0.0),
+    )))
+}
+
+fn marker(x: f64, y: f64) -> Entity {
+    let mut ent = Entity::new(EntityType::ModelPoint(ModelPoint::new(Point::new(
+        x, y, 0.0,
+    ))));
+    ent.common.layer = "TERMINALS".into();
+    ent
+}
+
+//a text attribute of an xml element
+fn attr<'a>(elem: &'a str, name: &str) -> &'a str {
+    let start = elem.find(&format!(" {name}=\"")).unwrap() + name.len() + 3;
+    let len = elem[start..].find('"').unwrap();
+    &elem[start..start + len]
+}
+
+#[test]
+fn point_terminals_face_the_closest_side_of_the_element() {
+    let mut drw = Drawing::new();
+    //an outline 40 wide and 30 high, well away from the origin
+    for (x1, y1, x2, y2) in [
+        (100.0, 100.0, 140.0, 100.0),
+        (140.0, 100.0, 140.0, 130.0),
+        (140.0, 130.0, 100.0, 130.0),
+        (100.0, 130.0, 100.0, 100.0),
+    ] {
+        drw.add_entity(line(x1, y1, x2, y2));
+    }
+    for (x, y) in [
+        (120.0, 129.0),
+        (139.0, 115.0),
+        (120.0, 101.0),
+        (101.0, 115.0),
+    ] {
+        drw.add_entity(marker(x, y));
+    }
+
+    let mut options = ConversionOptions::default();
+    options.scale = Some(1.0);
+    options.terminal_layers = vec!["terminals".into()];
+    let xml = convert("test", &drw, &options).unwrap().to_string();
+
+    //the y axis is flipped, so the top of the drawing is north
+    let terminals: Vec<(&str, &str, &str)> = xml
+        .split("<terminal")
+        .skip(1)
+        .map(|elem| {
+            let elem = &elem[..elem.find("/>").unwrap()];
+            (attr(elem, "x"), attr(elem, "y"), attr(elem, "orientation"))
+        })
+        .collect();
+    assert_eq!(
+        terminals,
+        [
+            ("120", "-129", "n"),
+            ("139", "-115", "e"),
+            ("120", "-101", "s"),
+            ("101", "-115", "w"),
+        ]
+    );
+}
@@ -0,0 +1,80 @@
+use dxf::entities::{AttributeDefinition, Entity, EntityType, MText, Text};
+use dxf::{Drawing, Point};
+use dxf2elmt::{convert, ConversionOptions, TextMode};
+
+//the point size in the font of every static text in an element
+fn font_sizes(drw: &Drawing, scale: f64) -> Vec<String> {
+    let mut options = ConversionOptions::default();
+    options.scale = Some(scale);
+    options.text_mode = TextMode::Static;
+
+    let xml = convert("test", drw, &options).unwrap().to_string();
+    xml.split("<text ")
+        .skip(1)
+        .map(|elem| {
+            let font = &elem[elem.find(" font=\"").unwrap() + 7..];
+            font.split(',').nth(1).unwrap().to_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn static_text_size_follows_the_text_height() {
+    let mut drw = Drawing::new();
+    drw.add_entity(Entity::new(EntityType::Text(Text {
+        location: Point::new(10.0, 10.0, 0.0),
+        text_height: 5.0,
+        value: "TEXT".into(),
+        ..Default::default()
+    })));
+    drw.add_entity(Entity::new(EntityType::MText(MText {
+        insertion_point: Point::new(10.0, 30.0, 0.0),
+        initial_text_height: 4.0,
+        text: "MTEXT".into(),
+        ..Default::default()
+    })));
+
+    assert_eq!(font_sizes(&drw, 1.0), ["5", "4"]);
+    //and gets scaled along with the rest of the drawing
+    assert_eq!(font_sizes(&drw, 2.0), ["10", "8"]);
+}
+
+#[test]
+fn mapped_attribute_tags_become_element_informations() {
+    let mut drw = Drawing::new();
+    for (tag, value) in [("tag", "K1"), ("PARTNO", "X-100"), ("NOTE", "spare")] {
+        drw.add_entity(Entity::new(EntityType::AttributeDefinition(
+            AttributeDefinition {
+                text_tag: tag.into(),
+                value: value.into(),
+                text_height: 2.5,
+                ..Default::default()
+            },
+        )));
+    }
+    let mut options = ConversionOptions::default();
+    options
+        .info_names
+        .push(("PARTNO".into(), "manufacturer_reference".into()));
+
+    //the name and value of every element information
+    let xml = convert("test", &drw, &options).unwrap().to_string();
+    let infos: Vec<(&str, &str)> = xml
+        .split("<elementInformation ")
+        .skip(1)
+        .map(|elem| {
+            let name = &elem[elem.find(" name=\"").unwrap() + 7..];
+            let value = &elem[elem.find('>').unwrap() + 1..];
+            (
+                &name[..name.find('"').unwrap()],
+                &value[..value.find('<').unwrap()],
+            )
+        })
+        .collect();
+
+    //TAG is one of the defaults, in any case, and NOTE isn't mapped onto anything
+    assert_eq!(
+        infos,
+        [("label", "K1"), ("manufacturer_reference", "X-100")]
+    );
+}